@@ -43,6 +43,12 @@ def main():
         help="how many cores the schedule should allocate for",
     )
 
+    parser.add_argument(
+        "--seed",
+        type=int,
+        help="the seed for random decisions, given when parasol is run with --seed",
+    )
+
     args = parser.parse_args()
 
     sched = schedule(args.features, args.cores)
@@ -41,6 +41,12 @@ def main():
     parser = argparse.ArgumentParser(description="AI Solver Scheduler")
     parser.add_argument("features", type=parse_comma_separated_floats)
     parser.add_argument("-p", required=True, type=int, dest="cores")
+    # The prediction is deterministic, so the seed is only accepted
+    parser.add_argument(
+        "--seed",
+        type=int,
+        help="the seed for random decisions, given when parasol is run with --seed",
+    )
     args = parser.parse_args()
 
     sched = schedule(args.features, args.cores)
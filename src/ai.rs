@@ -1,4 +1,5 @@
 pub mod commandline;
+pub mod http;
 use crate::{
     scheduler::{Portfolio, SolverInfo},
     solvers,
@@ -9,18 +10,24 @@ pub type Features = Vec<f32>;
 pub enum Error {
     #[error("{0}")]
     Other(String),
+    /// The AI could not be reached, so the static schedule is used instead
+    #[error("{0}")]
+    Unavailable(String),
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
 
 pub trait Ai {
-    fn schedule(&mut self, features: &Features, cores: usize) -> Result<Portfolio>;
+    async fn schedule(&mut self, features: &Features, cores: usize) -> Result<Portfolio>;
+
+    /// Seeds the random decisions of the AI. AIs that are deterministic ignore it.
+    fn set_seed(&mut self, _seed: u64) {}
 }
 
 pub struct SimpleAi {}
 
 impl Ai for SimpleAi {
-    fn schedule(&mut self, _features: &Features, cores: usize) -> Result<Portfolio> {
+    async fn schedule(&mut self, _features: &Features, cores: usize) -> Result<Portfolio> {
         Ok(solvers::ALL_IDS
             .iter()
             .take(cores)
@@ -2,35 +2,53 @@ use itertools::Itertools;
 
 use super::{Error, Features, Result};
 use crate::{args::Verbosity, logging, scheduler::Portfolio, static_schedule::parse_schedule};
-use std::process::Command;
+use std::time::{Duration, Instant};
+use tokio::process::Command;
+
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
 
 pub struct Ai {
     pub command_name: String,
     pub verbosity: Verbosity,
+    /// How long the command is allowed to run before it is killed
+    pub timeout: Duration,
+    /// Passed to the command with `--seed`
+    pub seed: Option<u64>,
 }
 
 impl Ai {
-    pub fn new(command_name: String, verbosity: Verbosity) -> Self {
+    pub fn new(command_name: String, verbosity: Verbosity, timeout: Duration) -> Self {
         Self {
             command_name,
             verbosity,
+            timeout,
+            seed: None,
         }
     }
 }
 
 impl super::Ai for Ai {
-    fn schedule(&mut self, features: &Features, cores: usize) -> Result<Portfolio> {
+    async fn schedule(&mut self, features: &Features, cores: usize) -> Result<Portfolio> {
         logging::info!("AI info: Using command {}", self.command_name);
         let mut cmd = Command::new(&self.command_name);
+        cmd.kill_on_drop(true);
         cmd.arg("-p").arg(cores.to_string());
+        if let Some(seed) = self.seed {
+            cmd.arg("--seed").arg(seed.to_string());
+        }
         cmd.arg(features_to_arg(features));
 
-        let output = cmd.output().map_err(|e| {
-            Error::Other(format!(
-                "Failed to get command output for '{}': {e}",
-                self.command_name
-            ))
-        })?;
+        let start = Instant::now();
+        let output = tokio::time::timeout(self.timeout, cmd.output())
+            .await
+            .map_err(|_| Error::Other("AI command timed out".to_string()))?
+            .map_err(|e| {
+                Error::Other(format!(
+                    "Failed to get command output for '{}': {e}",
+                    self.command_name
+                ))
+            })?;
+        logging::info!("AI command took {:?}", start.elapsed());
 
         if self.verbosity >= Verbosity::Error {
             print_stderr(output.stderr);
@@ -47,6 +65,10 @@ impl super::Ai for Ai {
         logging::info!("AI schedule: {:?}", sched);
         sched
     }
+
+    fn set_seed(&mut self, seed: u64) {
+        self.seed = Some(seed);
+    }
 }
 
 fn features_to_arg(features: &Features) -> String {
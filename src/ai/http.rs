@@ -0,0 +1,95 @@
+use serde::Serialize;
+
+use super::{Error, Features, Result};
+use crate::{logging, scheduler::Portfolio, static_schedule::parse_schedule};
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How often a request is retried after a transient error
+const RETRIES: usize = 1;
+
+pub struct Ai {
+    pub url: String,
+    client: reqwest::Client,
+    seed: Option<u64>,
+}
+
+#[derive(Serialize)]
+struct Request<'a> {
+    features: &'a Features,
+    cores: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<u64>,
+}
+
+impl Ai {
+    pub fn new(url: String, timeout: Duration) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(timeout)
+            .build()
+            .map_err(|e| Error::Other(format!("Failed to create the HTTP client: {e}")))?;
+        Ok(Self {
+            url,
+            client,
+            seed: None,
+        })
+    }
+
+    async fn request_schedule(&self, features: &Features, cores: usize) -> reqwest::Result<String> {
+        self.client
+            .post(&self.url)
+            .json(&Request {
+                features,
+                cores,
+                seed: self.seed,
+            })
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<String>()
+            .await
+    }
+}
+
+impl super::Ai for Ai {
+    async fn schedule(&mut self, features: &Features, cores: usize) -> Result<Portfolio> {
+        logging::info!("AI info: Using HTTP endpoint {}", self.url);
+
+        let start = Instant::now();
+        let mut attempt = 0;
+        let response = loop {
+            match self.request_schedule(features, cores).await {
+                Ok(response) => break response,
+                Err(e) if attempt < RETRIES && is_transient(&e) => {
+                    logging::warning!("AI request to '{}' failed, retrying: {e}", self.url);
+                    attempt += 1;
+                }
+                Err(e) => {
+                    return Err(Error::Unavailable(format!(
+                        "AI request to '{}' failed: {e}",
+                        self.url
+                    )));
+                }
+            }
+        };
+        logging::info!("AI request took {:?}", start.elapsed());
+
+        let sched = parse_schedule(&response)
+            .map_err(|e| Error::Other(format!("Failed to parse as schedule: {e}")));
+        logging::info!("AI schedule: {:?}", sched);
+        sched
+    }
+
+    fn set_seed(&mut self, seed: u64) {
+        self.seed = Some(seed);
+    }
+}
+
+fn is_transient(error: &reqwest::Error) -> bool {
+    error.is_timeout()
+        || error.is_connect()
+        || error
+            .status()
+            .is_some_and(|status| status.is_server_error())
+}
@@ -1,7 +1,11 @@
 use clap::{Parser, ValueEnum};
-use std::{collections::HashMap, fmt, path::PathBuf, process::exit};
 
-use crate::logging;
+use crate::config::RestartStrategy;
+use std::{
+    collections::HashMap,
+    fmt,
+    path::{Path, PathBuf},
+};
 
 #[derive(Parser, Debug, Clone)]
 #[command(author, version, about)]
@@ -26,7 +30,13 @@ pub struct RunArgs {
     pub model: PathBuf,
 
     /// The MiniZinc data file corresponding to the model file
-    pub data: Option<PathBuf>,
+    #[arg(value_name = "DATA")]
+    pub data_file: Option<PathBuf>,
+
+    /// Additional MiniZinc data files. Can be given multiple times, and the files are passed to MiniZinc after
+    /// the positional data file in the given order.
+    #[arg(short = 'd', long, help_heading = "Input Files")]
+    pub data: Vec<PathBuf>,
 
     /// Optional path to a solver compiler priority configuration file
     #[arg(long, help_heading = "Input Files")]
@@ -49,6 +59,16 @@ pub struct RunArgs {
     #[arg(long, help_heading = "AI Configuration")]
     pub ai_config: Option<String>,
 
+    /// The seed for the scheduling decisions of the AI, such that a run can be reproduced
+    #[arg(long, help_heading = "AI Configuration")]
+    pub seed: Option<u64>,
+
+    /// Compares the AI with a second one of the same kind with this configuration (in the format of
+    /// `--ai-config`). Each of them schedules half of the cores, and the metrics are tagged with the session
+    /// (`a` or `b`) that scheduled the solver.
+    #[arg(long, value_name = "AI_CONFIG", help_heading = "AI Configuration")]
+    pub ab_test_ai: Option<String>,
+
     // === Output ===
     #[arg(
         long,
@@ -64,15 +84,50 @@ pub struct RunArgs {
     #[arg(long, help_heading = "Output")]
     pub output_objective: bool,
 
+    /// Write the solutions and the final status to this file instead of stdout. The file is truncated at startup.
+    #[arg(long, value_name = "PATH", help_heading = "Output")]
+    pub output_file: Option<PathBuf>,
+
+    /// Also write the solutions to stdout when `--output-file` is given
+    #[arg(long, requires = "output_file", help_heading = "Output")]
+    pub tee_output: bool,
+
+    /// Keep the best solution found so far in this file, which is replaced atomically on every new best solution
+    #[arg(long, value_name = "PATH", help_heading = "Output")]
+    pub solution_file: Option<PathBuf>,
+
+    /// Only write the `--solution-file` once, when the solve ends
+    #[arg(long, requires = "solution_file", help_heading = "Output")]
+    pub solution_on_exit_only: bool,
+
+    /// Prints a `% from: <solver>` comment before every solution, naming the solver that found it
+    #[arg(long, help_heading = "Output")]
+    pub tag_solutions: bool,
+
     // === Execution ===
-    /// The number of cores parasol should use
-    #[arg(short = 'p', default_value = "2", help_heading = "Execution")]
-    pub cores: usize,
+    /// The number of cores parasol should use, or `auto` to use all available cores (the default)
+    #[arg(
+        short = 'p',
+        long,
+        default_value = "auto",
+        value_parser = parse_cores,
+        help_heading = "Execution"
+    )]
+    pub cores: Cores,
 
     /// Pin the yuck solver processes to specific CPU cores. The yuck is written in java, hence it can use more cpu than it was given. This guarantees that we never use more than the allowed cpu (except for printing to stdout)
     #[arg(long, help_heading = "Execution")]
     pub pin_yuck: bool,
 
+    /// Run all discovered solvers in parallel until the time limit, and print a JSON table of their results.
+    /// No AI or schedule is used.
+    #[arg(long, requires = "time_limit", help_heading = "Execution")]
+    pub benchmark: bool,
+
+    /// Compile the initial schedule and print the solvers that would be started, without starting them
+    #[arg(long, help_heading = "Execution")]
+    pub dry_run: bool,
+
     /// Enable free search for all solvers
     #[arg(long, short = 'f', help_heading = "Execution")]
     pub ignore_search: bool,
@@ -85,10 +140,63 @@ pub struct RunArgs {
     #[arg(long, default_value = "discover", help_heading = "Execution")]
     pub solver_config_mode: SolverConfigMode,
 
+    /// Extra flags passed to a solver, in addition to the ones from the config.
+    /// The format is: <solver>=<flag1>,<flag2>,... and it can be given multiple times.
+    #[arg(long, value_parser = parse_extra_fzn_flags, help_heading = "Execution")]
+    pub extra_fzn_flags: Vec<(String, Vec<String>)>,
+
+    /// Only warn when a solver reports that the problem is unsatisfiable, and keep the other solvers running
+    /// until all of them report it. Useful when a solver might wrongly report unsatisfiability.
+    #[arg(long, help_heading = "Execution")]
+    pub warn_on_unsatisfiable: bool,
+
+    /// Stop after this many distinct solutions have been printed. Without it, only the first solution of a
+    /// satisfaction problem is printed.
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..), help_heading = "Execution")]
+    pub max_solutions: Option<u64>,
+
+    /// Print a solution of a satisfaction problem again when another solver finds it too
+    #[arg(long, help_heading = "Execution")]
+    pub allow_duplicates: bool,
+
+    /// Require solvers to strictly improve on the best objective found so far, instead of allowing equal solutions
+    #[arg(long, help_heading = "Execution")]
+    pub strict_bounds: bool,
+
+    /// Only run the solvers of the schedules with these names, e.g. `gecode,coinbc`
+    #[arg(long, value_delimiter = ',', help_heading = "Execution")]
+    pub solver_filter: Vec<String>,
+
+    /// Abort if a schedule file names a solver that is not installed, instead of only warning about it
+    #[arg(long, help_heading = "Execution")]
+    pub strict_solver_validation: bool,
+
+    /// Do not use the on-disk cache of FlatZinc compilations in `$XDG_CACHE_HOME/parasol/fzn`
+    #[arg(long, help_heading = "Execution")]
+    pub no_fzn_cache: bool,
+
+    /// Do not use the on-disk cache of feature vectors in `$XDG_CACHE_HOME/parasol/features`
+    #[arg(long, help_heading = "Execution")]
+    pub no_feature_cache: bool,
+
+    /// Compile for every discovered solver at startup, such that a schedule can switch to any solver right away
+    #[arg(long, help_heading = "Execution")]
+    pub precompile_all: bool,
+
     /// Whether it should kill solvers if you are nearing the system memory limit
     #[arg(long, help_heading = "Execution")]
     pub enforce_memory: bool,
 
+    /// A soft memory limit (in MiB) per solver. Solvers that use more are suspended.
+    /// This also works without --enforce-memory, and takes priority over the system memory limit.
+    #[arg(long, help_heading = "Execution")]
+    pub memory_per_solver: Option<u64>,
+
+    /// Runs every solver with a memory limit in its own cgroup (Linux with cgroups v2 only), such that the
+    /// kernel enforces the limit instead of the periodic memory enforcer
+    #[arg(long, help_heading = "Execution")]
+    pub cgroups: bool,
+
     // === Timing ===
     /// The minimum time (in seconds) the initial static schedule will be run before using the AI's schedule
     #[arg(long, default_value = "5", help_heading = "Timing")]
@@ -98,36 +206,109 @@ pub struct RunArgs {
     #[arg(long, default_value = "7", help_heading = "Timing")]
     pub restart_interval: u64,
 
+    /// How the solvers are restarted with a better objective. Overrides the config file, and defaults to immediate.
+    #[arg(long, value_enum, help_heading = "Timing")]
+    pub restart_strategy: Option<RestartStrategy>,
+
+    /// Restart the solvers as soon as a better objective is found, instead of waiting for the next restart interval
+    #[arg(long, help_heading = "Timing")]
+    pub rerun_on_improvement: bool,
+
     /// The time (in seconds) before we skip extracting the features and stop using the static schedule, and instead use the timeout schedule.
     /// Warning: if static_runtime set higher than feature_timeout, then static_runtime will be used instead.
     #[arg(long, default_value = "10", help_heading = "Timing")]
     pub feature_timeout: u64,
 
+    /// The time (in seconds) after which the run is stopped. The time starts once the initial schedule is compiled.
+    /// Exits with code 0 if a solution was found by then, and with code 2 (unknown) otherwise.
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..), help_heading = "Timing")]
+    pub time_limit: Option<u64>,
+
     // === Paths ===
     #[command(flatten)]
     pub minizinc: MiniZincArgs,
 
+    /// The path to the mzn2feat feature extractor executable.
+    #[arg(long, default_value = "mzn2feat", help_heading = "Paths")]
+    pub mzn2feat_exe: PathBuf,
+
+    /// The path to a TOML configuration file, which overrides the default configuration.
+    /// See `examples/config.toml` for the available options.
+    #[arg(long, help_heading = "Paths")]
+    pub config: Option<PathBuf>,
+
     /// The path to the static schedule file.
-    /// The file needs to be a CSV (without a header) in the format of `<solver>,<cores>`.
+    /// A `.toml` file (or a `.json` file with an object) lists the solvers as tables with a `name`, `cores` and
+    /// optional `args`, `hint_file` and `time_budget` (in seconds).
+    /// A `.json` file with an array is a portfolio file with an `id`, `cores` and optional `flags`, `version`,
+    /// `description`, `fallback`, `hint_file` and `time_budget` per solver.
+    /// Any other file is a CSV (without a header) in the format of `<solver>,<cores>`
+    /// or `<solver>,<cores>,<time budget in seconds>`.
     /// If not provided, a default static schedule will be used.
-    #[arg(long, help_heading = "Paths")]
+    #[arg(long, visible_alias = "portfolio-file", help_heading = "Paths")]
     pub static_schedule: Option<PathBuf>,
 
     /// The path to the timeout schedule file. This schedule will be run if the compilation or the feature extraction takes too long
-    /// The file needs to be a CSV (without a header) in the format of `<solver>,<cores>`.
+    /// It has the same formats as `--static-schedule`, including the time budgets.
     /// If not provided, a default timeout schedule will be used.
     #[arg(long, help_heading = "Paths")]
     pub timeout_schedule: Option<PathBuf>,
 
     // === Debugging ===
+    /// The logging verbosity. The flag takes precedence over the environment variable.
     #[arg(
         long,
         short = 'v',
         value_enum,
         default_value = "warning",
+        env = "PARASOL_LOG_LEVEL",
+        ignore_case = true,
         help_heading = "Debugging"
     )]
     pub verbosity: Verbosity,
+
+    /// The format of the log messages on stderr
+    #[arg(long, value_enum, default_value = "text", help_heading = "Debugging")]
+    pub log_format: LogFormat,
+
+    /// Serves live metrics in the Prometheus text format on `http://127.0.0.1:<PORT>/metrics`
+    #[arg(long, value_name = "PORT", help_heading = "Debugging")]
+    pub metrics_port: Option<u16>,
+}
+
+impl RunArgs {
+    /// The number of cores to use, detected from the available parallelism if it is not set
+    pub fn cores(&self) -> usize {
+        match self.cores {
+            Cores::Count(cores) => cores,
+            Cores::Auto => std::thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(2),
+        }
+    }
+
+    /// All data files in the order they are passed to MiniZinc
+    pub fn data_files(&self) -> impl Iterator<Item = &Path> {
+        self.data_file
+            .as_deref()
+            .into_iter()
+            .chain(self.data.iter().map(PathBuf::as_path))
+    }
+
+    /// The arguments of `parasol run <model>` with the defaults of every other flag, except for a fixed number
+    /// of cores and no logging, such that tests do not depend on the machine or the environment
+    #[cfg(test)]
+    pub fn for_testing(model: PathBuf) -> Self {
+        let Command::Run(mut args) =
+            Cli::parse_from(["parasol".as_ref(), "run".as_ref(), model.as_os_str()]).command
+        else {
+            unreachable!("the arguments are parsed as the run command");
+        };
+        args.ai = Ai::Simple;
+        args.cores = Cores::Count(2);
+        args.verbosity = Verbosity::Quiet;
+        args
+    }
 }
 
 #[derive(clap::Args, Debug, Clone)]
@@ -150,18 +331,38 @@ pub enum Ai {
     /// Use the simple AI
     Simple,
     /// Use the command line AI. MUST specify ai-config with `command=<command-path>`.
+    /// Optionally, `timeout=<seconds>` sets how long the command may run (default: 5 seconds).
     CommandLine,
+    /// Use an AI behind an HTTP endpoint. MUST specify ai-config with `url=<url>`.
+    /// The features are POSTed as `{"features": [...], "cores": N}`, and the response must be a JSON string
+    /// with the schedule in the static schedule format.
+    /// Optionally, `timeout=<seconds>` sets how long a request may take (default: 2 seconds).
+    Http,
 }
 
-#[derive(Debug, Clone, ValueEnum)]
+#[derive(Debug, Clone, Copy, ValueEnum)]
 pub enum OutputMode {
     Dzn,
+    /// Every solution and status is printed as a JSON object on its own line, like MiniZinc's `--json-stream`
+    JsonStream,
+    /// The solutions are formatted by the output item of the model, for problems that need their own format
+    Model,
+}
+
+impl OutputMode {
+    /// Whether MiniZinc needs the `--json-stream` flag when running the solver
+    pub fn is_json_stream(&self) -> bool {
+        matches!(self, OutputMode::JsonStream)
+    }
 }
 
+/// Formats the output mode as the value of MiniZinc's `--output-mode` flag
 impl fmt::Display for OutputMode {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             OutputMode::Dzn => write!(f, "dzn"),
+            OutputMode::JsonStream => write!(f, "json"),
+            OutputMode::Model => write!(f, "item"),
         }
     }
 }
@@ -174,6 +375,14 @@ pub enum Verbosity {
     Info = 3,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum LogFormat {
+    /// `LEVEL: [file:line] message`
+    Text,
+    /// A JSON object per line with the level, file, line, message and a unix timestamp in seconds
+    Json,
+}
+
 #[derive(Debug, Clone, ValueEnum)]
 pub enum SolverConfigMode {
     /// Use the cached solver configs (The cache must be generated beforehand)
@@ -182,16 +391,122 @@ pub enum SolverConfigMode {
     Discover,
 }
 
-pub fn parse_ai_config(config: Option<&str>) -> HashMap<String, String> {
+fn parse_extra_fzn_flags(s: &str) -> Result<(String, Vec<String>), String> {
+    let (solver, flags) = s
+        .split_once('=')
+        .ok_or_else(|| format!("Extra solver flags are missing '=': '{s}'"))?;
+    let flags = flags.split(',').map(str::to_owned).collect();
+    Ok((solver.to_owned(), flags))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cores {
+    /// Use all available cores
+    Auto,
+    Count(usize),
+}
+
+fn parse_cores(s: &str) -> Result<Cores, String> {
+    if s.eq_ignore_ascii_case("auto") {
+        return Ok(Cores::Auto);
+    }
+    match s.parse::<usize>() {
+        Ok(0) => Err("the number of cores must be at least 1".to_owned()),
+        Ok(cores) => Ok(Cores::Count(cores)),
+        Err(_) => Err(format!("'{s}' is neither a number nor 'auto'")),
+    }
+}
+
+/// Parses `key=value,key2=value2`, where a missing or empty configuration has no keys
+pub fn parse_ai_config(config: Option<&str>) -> Result<HashMap<String, String>, String> {
+    let Some(config) = config.filter(|config| !config.is_empty()) else {
+        return Ok(HashMap::new());
+    };
     config
-        .unwrap_or_default()
         .split(',')
         .map(|key_value| {
-            let Some((key, value)) = key_value.split_once('=') else {
-                logging::error_msg!("Key-value pair is missing '=' in the AI configuration. The key-value: '{key_value}'");
-                exit(1);
-            };
-            (key.to_owned(), value.to_owned())
+            let (key, value) = key_value.split_once('=').ok_or_else(|| {
+                format!("Key-value pair is missing '=' in the AI configuration: '{key_value}'")
+            })?;
+            Ok((key.to_owned(), value.to_owned()))
         })
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_error(args: &[&str]) -> String {
+        let args = ["parasol", "run", "model.mzn"].iter().chain(args);
+        Cli::try_parse_from(args)
+            .expect_err("the arguments should be rejected")
+            .to_string()
+    }
+
+    #[test]
+    fn test_for_testing_uses_the_defaults() {
+        let args = RunArgs::for_testing("model.mzn".into());
+        assert_eq!(args.model, PathBuf::from("model.mzn"));
+        assert_eq!(args.data_files().count(), 0);
+        assert_eq!(args.cores(), 2);
+        assert_eq!(args.time_limit, None);
+    }
+
+    #[test]
+    fn test_time_limit_zero_is_rejected() {
+        assert!(parse_error(&["--time-limit", "0"]).contains("--time-limit"));
+    }
+
+    #[test]
+    fn test_max_solutions_zero_is_rejected() {
+        assert!(parse_error(&["--max-solutions", "0"]).contains("--max-solutions"));
+    }
+
+    #[test]
+    fn test_zero_cores_is_rejected() {
+        assert!(parse_error(&["--cores", "0"]).contains("the number of cores must be at least 1"));
+    }
+
+    #[test]
+    fn test_extra_fzn_flags_missing_equals() {
+        assert!(parse_error(&["--extra-fzn-flags", "gecode"]).contains("missing '='"));
+    }
+
+    #[test]
+    fn test_benchmark_requires_time_limit() {
+        assert!(parse_error(&["--benchmark"]).contains("--time-limit"));
+    }
+
+    #[test]
+    fn test_solution_on_exit_only_needs_solution_file() {
+        assert!(parse_error(&["--solution-on-exit-only"]).contains("--solution-file"));
+    }
+
+    #[test]
+    fn test_ai_config_missing_equals() {
+        let error = parse_ai_config(Some("command")).expect_err("'command' has no value");
+        assert!(error.contains("missing '='"));
+    }
+
+    #[test]
+    fn test_ai_config_key_values() {
+        let config = parse_ai_config(Some("command=./knn.py,timeout=10")).unwrap();
+        assert_eq!(
+            config,
+            HashMap::from([
+                ("command".to_owned(), "./knn.py".to_owned()),
+                ("timeout".to_owned(), "10".to_owned()),
+            ])
+        );
+        // Only the first '=' separates the key from the value
+        let config = parse_ai_config(Some("command=./ai --mode=fast")).unwrap();
+        assert_eq!(config["command"], "./ai --mode=fast");
+    }
+
+    #[test]
+    fn test_missing_ai_config_is_empty() {
+        assert_eq!(parse_ai_config(None), Ok(HashMap::new()));
+        assert_eq!(parse_ai_config(Some("")), Ok(HashMap::new()));
+    }
+}
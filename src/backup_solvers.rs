@@ -1,4 +1,10 @@
-use crate::args::RunArgs;
+use crate::args::{OutputMode, RunArgs};
+use crate::logging;
+use crate::model_parser::{ObjectiveDomain, ObjectiveType};
+use crate::solution_writer::SolutionWriter;
+use crate::solver_output::{self, Output};
+use std::process::Stdio;
+use tokio::io::{AsyncBufReadExt, BufReader};
 use tokio::process::Command;
 
 #[derive(Debug, thiserror::Error)]
@@ -7,28 +13,39 @@ pub enum Error {
     CommandFailed,
     #[error("IO error while running backup solver: {0}")]
     Io(#[from] std::io::Error),
+    #[error("failed to capture the backup solver's stdout")]
+    StdoutMissing,
 }
 pub type Result<T> = std::result::Result<T, Error>;
 
-pub async fn run_backup_solver(args: &RunArgs, cores: usize) -> Result<()> {
+pub async fn run_backup_solver(
+    args: &RunArgs,
+    cores: usize,
+    solution_writer: &SolutionWriter,
+) -> Result<()> {
     let mut cmd = Command::new(&args.minizinc.minizinc_exe);
     cmd.kill_on_drop(true);
     cmd.arg("--solver").arg("cp-sat");
 
     cmd.arg(&args.model);
-    if let Some(data) = &args.data {
-        cmd.arg(data);
-    }
+    cmd.args(args.data_files());
 
     cmd.arg("-i").arg("-f");
 
     cmd.arg("--output-objective");
     cmd.arg("--output-mode");
     cmd.arg(args.output_mode.to_string());
+    if args.output_mode.is_json_stream() {
+        cmd.arg("--json-stream");
+    }
 
     cmd.arg("-p").arg(cores.to_string());
 
+    cmd.stdout(Stdio::piped());
     let mut child = cmd.spawn()?;
+    let stdout = child.stdout.take().ok_or(Error::StdoutMissing)?;
+
+    forward_output(stdout, args.output_mode, solution_writer).await?;
 
     let status = child.wait().await?;
 
@@ -38,3 +55,33 @@ pub async fn run_backup_solver(args: &RunArgs, cores: usize) -> Result<()> {
         Err(Error::CommandFailed)
     }
 }
+
+async fn forward_output(
+    stdout: tokio::process::ChildStdout,
+    output_mode: OutputMode,
+    solution_writer: &SolutionWriter,
+) -> Result<()> {
+    // The objective type is not needed for forwarding the output, and the backup solver is
+    // often used exactly because the model could not be inspected.
+    let mut parser = solver_output::OutputParser::new(
+        output_mode,
+        ObjectiveType::Satisfy,
+        ObjectiveDomain::default(),
+    );
+    let mut lines = BufReader::new(stdout).lines();
+
+    while let Some(line) = lines.next_line().await? {
+        match parser.next_line(&line) {
+            Ok(Some(Output::Solution(solution))) => {
+                solution_writer.write_solution(&solution, output_mode).await
+            }
+            Ok(Some(Output::Status(status))) => {
+                solution_writer.write_status(status, output_mode).await
+            }
+            Ok(None) => {}
+            Err(e) => logging::error!(e.into()),
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,122 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::Serialize;
+use tokio_util::sync::CancellationToken;
+
+use crate::args::RunArgs;
+use crate::config::Config;
+use crate::model_parser::ObjectiveValue;
+use crate::mzn_to_fzn::compilation_manager::CompilationManager;
+use crate::scheduler::{Portfolio, Scheduler, SolverInfo};
+use crate::signal_handler::SignalEvent;
+use crate::solution_writer::SolutionWriter;
+use crate::{logging, solver_config, solver_manager, solvers};
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("Solver manager error")]
+    SolverManager(#[from] solver_manager::Error),
+    #[error("no solvers were discovered to benchmark")]
+    NoSolvers,
+    #[error("failed to serialize the benchmark results")]
+    Json(#[from] serde_json::Error),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// A row of the table that is printed at the end of the benchmark
+#[derive(Serialize)]
+struct BenchmarkResult {
+    solver: String,
+    best_objective: Option<ObjectiveValue>,
+    /// In seconds from the start of the benchmark
+    time_to_first_solution: Option<f64>,
+    solution_count: u64,
+}
+
+/// Runs every discovered solver with a single core until the time limit, and prints their results as JSON
+pub async fn benchmark(
+    args: &RunArgs,
+    config: Config,
+    solvers: Arc<solver_config::Solvers>,
+    solution_writer: SolutionWriter,
+    program_cancellation_token: CancellationToken,
+    suspend_and_resume_signal_rx: tokio::sync::mpsc::UnboundedReceiver<SignalEvent>,
+) -> Result<()> {
+    let portfolio = benchmark_portfolio(args, &solvers);
+    if portfolio.is_empty() {
+        return Err(Error::NoSolvers);
+    }
+
+    let compilation_manager = Arc::new(CompilationManager::new(
+        Arc::new(args.clone()),
+        solvers.clone(),
+        config.max_compilation_retries,
+    ));
+    let mut scheduler = Scheduler::new(
+        args,
+        &config,
+        solvers,
+        compilation_manager,
+        solution_writer,
+        program_cancellation_token.clone(),
+        suspend_and_resume_signal_rx,
+    )
+    .await?;
+
+    logging::info!(
+        "benchmarking {} solvers: {:?}",
+        portfolio.len(),
+        portfolio.iter().map(|info| &info.name).collect::<Vec<_>>()
+    );
+    let names: Vec<String> = portfolio.iter().map(|info| info.name.clone()).collect();
+    let apply_cancellation_token = scheduler.create_apply_token();
+    if let Err(errors) = scheduler
+        .apply(portfolio, apply_cancellation_token, false)
+        .await
+    {
+        errors.into_iter().for_each(|e| logging::error!(e.into()));
+    }
+
+    // The time limit is required for the benchmark
+    let time_limit = Duration::from_secs(args.time_limit.unwrap_or_default());
+    tokio::select! {
+        _ = tokio::time::sleep(time_limit) => {}
+        _ = program_cancellation_token.cancelled() => {}
+    }
+
+    let solver_manager = &scheduler.solver_manager;
+    if let Err(errors) = solver_manager.stop_all_solvers().await {
+        errors.into_iter().for_each(|e| logging::warning!("{e}"));
+    }
+    solver_manager.wait_for_pending_force_kills().await;
+
+    let metrics = solver_manager.metrics().await;
+    solver_manager.dump_metrics().await;
+    let results: Vec<BenchmarkResult> = names
+        .into_iter()
+        .map(|solver| {
+            let metrics = metrics.get(&solver).cloned().unwrap_or_default();
+            BenchmarkResult {
+                solver,
+                best_objective: metrics.best_objective,
+                time_to_first_solution: metrics.time_to_first_solution,
+                solution_count: metrics.solution_count,
+            }
+        })
+        .collect();
+    println!("{}", serde_json::to_string_pretty(&results)?);
+    Ok(())
+}
+
+/// Every discovered solver with a single core, except parasol itself and the solvers that are filtered out
+fn benchmark_portfolio(args: &RunArgs, solvers: &solver_config::Solvers) -> Portfolio {
+    solvers
+        .iter()
+        .map(|solver| solver.id())
+        .filter(|id| *id != solvers::SELF_ID)
+        .filter(|id| args.solver_filter.is_empty() || args.solver_filter.iter().any(|f| f == id))
+        .map(|id| SolverInfo::new(id.to_owned(), 1))
+        .collect()
+}
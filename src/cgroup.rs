@@ -0,0 +1,163 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const CGROUP_MOUNT: &str = "/sys/fs/cgroup";
+
+/// How often removing a cgroup is tried while the killed processes in it have not exited yet
+const REMOVE_ATTEMPTS: u32 = 20;
+
+/// The cgroups v2 hierarchy for the solvers, which enforces their memory limits in the kernel, such that they
+/// cannot allocate past the limit before the memory enforcer runs.
+#[derive(Debug)]
+pub struct Cgroups {
+    /// The `parasol` cgroup, under which every solver gets its own cgroup
+    root: PathBuf,
+    memory_per_solver_bytes: Option<u64>,
+    /// Per solver name
+    solver_memory_limits_bytes: HashMap<String, u64>,
+}
+
+impl Cgroups {
+    /// Creates the `parasol` cgroup under the cgroup of this process, with the memory controller enabled such
+    /// that the cgroups of the solvers can be limited.
+    pub async fn new(
+        memory_per_solver_bytes: Option<u64>,
+        solver_memory_limits_bytes: HashMap<String, u64>,
+    ) -> Result<Self> {
+        let parent = Path::new(CGROUP_MOUNT).join(own_cgroup().await?.trim_start_matches('/'));
+        enable_memory_controller(&parent).await?;
+
+        let root = parent.join("parasol");
+        create_dir(&root).await?;
+        enable_memory_controller(&root).await?;
+
+        Ok(Self {
+            root,
+            memory_per_solver_bytes,
+            solver_memory_limits_bytes,
+        })
+    }
+
+    /// The lower one of the memory limit per solver and the limit for the named solver, in bytes
+    fn memory_limit(&self, solver_name: &str) -> Option<u64> {
+        let solver_limit = self.solver_memory_limits_bytes.get(solver_name).copied();
+        match (self.memory_per_solver_bytes, solver_limit) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        }
+    }
+
+    /// Moves the solver process into its own cgroup with the memory limit of the solver. Returns the path of the
+    /// cgroup, or `None` if the solver has no memory limit.
+    pub async fn add_solver(
+        &self,
+        solver_id: u64,
+        solver_name: &str,
+        pid: u32,
+    ) -> Result<Option<PathBuf>> {
+        let Some(limit) = self.memory_limit(solver_name) else {
+            return Ok(None);
+        };
+
+        // The pid keeps the cgroup of a restarted solver apart from the one of its previous process, which may
+        // still be removed
+        let path = self.root.join(format!("{solver_id}-{pid}"));
+        create_dir(&path).await?;
+        write(&path.join("memory.max"), limit.to_string()).await?;
+        write(&path.join("cgroup.procs"), pid.to_string()).await?;
+        Ok(Some(path))
+    }
+}
+
+/// Removes the cgroup of a solver, waiting for a short while for its processes to exit. It blocks, so it is meant
+/// to be called from the thread that force kills the solver.
+pub fn remove(path: &Path) -> Result<()> {
+    let mut attempt = 1;
+    loop {
+        match std::fs::remove_dir(path) {
+            Err(e) if e.kind() == std::io::ErrorKind::ResourceBusy && attempt < REMOVE_ATTEMPTS => {
+                attempt += 1;
+                std::thread::sleep(Duration::from_millis(50));
+            }
+            result => {
+                return result.map_err(|source| Error::Remove {
+                    path: path.to_path_buf(),
+                    source,
+                });
+            }
+        }
+    }
+}
+
+/// The path of the cgroup v2 of this process, relative to the cgroup mount
+async fn own_cgroup() -> Result<String> {
+    let content = tokio::fs::read_to_string("/proc/self/cgroup")
+        .await
+        .map_err(Error::ReadOwnCgroup)?;
+    // The unified hierarchy of cgroups v2 is the entry with the id 0 and no controllers
+    content
+        .lines()
+        .find_map(|line| line.strip_prefix("0::"))
+        .map(str::to_owned)
+        .ok_or(Error::NotCgroupV2)
+}
+
+async fn enable_memory_controller(cgroup: &Path) -> Result<()> {
+    let subtree_control = cgroup.join("cgroup.subtree_control");
+    let enabled = tokio::fs::read_to_string(&subtree_control)
+        .await
+        .is_ok_and(|controllers| controllers.split_whitespace().any(|c| c == "memory"));
+    if enabled {
+        return Ok(());
+    }
+    write(&subtree_control, "+memory".to_owned()).await
+}
+
+async fn create_dir(path: &Path) -> Result<()> {
+    match tokio::fs::create_dir(path).await {
+        // Left over from a previous run
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => Ok(()),
+        result => result.map_err(|source| Error::Create {
+            path: path.to_path_buf(),
+            source,
+        }),
+    }
+}
+
+async fn write(path: &Path, content: String) -> Result<()> {
+    tokio::fs::write(path, content)
+        .await
+        .map_err(|source| Error::Write {
+            path: path.to_path_buf(),
+            source,
+        })
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("failed to read the cgroup of this process")]
+    ReadOwnCgroup(#[source] std::io::Error),
+    #[error("this process is not in a cgroups v2 hierarchy")]
+    NotCgroupV2,
+    #[error("failed to create the cgroup '{path}'")]
+    Create {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("failed to write '{path}'")]
+    Write {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("failed to remove the cgroup '{path}'")]
+    Remove {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
@@ -1,16 +1,116 @@
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 
-use crate::{args::RunArgs, solver_config};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone)]
+use crate::{args::RunArgs, logging, solver_config};
+
+const DEFAULT_MEMORY_THRESHOLD: f64 = 0.9;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
     pub memory_enforcer_interval: u64,
     pub memory_threshold: f64,
+    /// The fraction of the memory under which suspended solvers may be resumed again, such that the solvers are
+    /// not suspended and resumed over and over when the memory usage is close to the threshold
+    pub memory_resume_threshold: f64,
+    /// The memory limit for the process tree of a single solver
+    pub memory_per_solver_bytes: Option<u64>,
+    /// Memory limits for the process trees of the named solvers. Solvers that use more are stopped.
+    pub solver_memory_limits_bytes: HashMap<String, u64>,
     pub solver_args: HashMap<String, Vec<String>>,
+    /// If set, the extracted feature vector must have exactly this length
+    pub expected_feature_length: Option<usize>,
+    /// How often a compilation is retried after a transient IO error
+    pub max_compilation_retries: u32,
+    /// If set, a running solver that has not improved its objective for this many seconds is suspended in favour
+    /// of a suspended solver
+    pub stall_timeout_secs: Option<u64>,
+    pub restart_strategy: RestartStrategy,
+    /// How often a solver that crashed is started again
+    pub max_solver_restarts: u32,
+    /// How many seconds a stopped solver is given to exit after `SIGTERM` before it is killed with `SIGKILL`
+    pub solver_kill_grace_secs: u64,
+}
+
+/// How the solvers are restarted when a better objective has been found
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum RestartStrategy {
+    /// Stop the old solvers before starting the new ones
+    #[default]
+    Immediate,
+    /// Start the new solvers first, and stop the old ones once a new one has produced output
+    Overlap,
+}
+
+/// The values that can be overridden in the TOML configuration file given with `--config`
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    memory_enforcer_interval: Option<u64>,
+    memory_threshold: Option<f64>,
+    memory_resume_threshold: Option<f64>,
+    /// In MiB, like `--memory-per-solver` and `solver_memory_limits`
+    memory_per_solver: Option<u64>,
+    #[serde(default)]
+    solver_memory_limits: HashMap<String, u64>,
+    /// Replaces the arguments of the given solvers
+    #[serde(default)]
+    solver_args: HashMap<String, Vec<String>>,
+    expected_feature_length: Option<usize>,
+    max_compilation_retries: Option<u32>,
+    stall_timeout_secs: Option<u64>,
+    restart_strategy: Option<RestartStrategy>,
+    max_solver_restarts: Option<u32>,
+    solver_kill_grace_secs: Option<u64>,
+}
+
+impl ConfigFile {
+    fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path).map_err(|source| Error::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let file: Self = toml::from_str(&content)?;
+
+        if file.memory_enforcer_interval == Some(0) {
+            return Err(Error::Invalid(
+                "memory_enforcer_interval must be greater than 0".to_owned(),
+            ));
+        }
+        if let Some(resume_threshold) = file.memory_resume_threshold {
+            let threshold = file.memory_threshold.unwrap_or(DEFAULT_MEMORY_THRESHOLD);
+            if !(resume_threshold > 0.0 && resume_threshold <= threshold) {
+                return Err(Error::Invalid(format!(
+                    "memory_resume_threshold must be in (0, memory_threshold], but is {resume_threshold}"
+                )));
+            }
+        }
+        if file.stall_timeout_secs == Some(0) {
+            return Err(Error::Invalid(
+                "stall_timeout_secs must be greater than 0".to_owned(),
+            ));
+        }
+        if let Some(threshold) = file.memory_threshold
+            && !(threshold > 0.0 && threshold <= 1.0)
+        {
+            return Err(Error::Invalid(format!(
+                "memory_threshold must be in (0, 1], but is {threshold}"
+            )));
+        }
+        Ok(file)
+    }
 }
 
 impl Config {
-    pub fn new(program_args: &RunArgs, solvers: &solver_config::Solvers) -> Self {
+    /// The defaults are overridden by the configuration file, which is overridden by the command-line arguments.
+    pub fn new(program_args: &RunArgs, solvers: &solver_config::Solvers) -> Result<Self> {
+        let file = match &program_args.config {
+            Some(path) => ConfigFile::load(path)?,
+            None => ConfigFile::default(),
+        };
+
         let mut solver_args = HashMap::new();
 
         for solver in solvers.iter() {
@@ -23,17 +123,155 @@ impl Config {
                 args.push("-a".to_owned());
             }
 
-            if program_args.ignore_search && supported_flags.f {
-                args.push("-f".to_string());
+            solver_args.insert(solver.id().to_owned(), args);
+        }
+        // A solver with `-f` in its arguments ignores the search annotations of the model
+        solver_args.extend(file.solver_args);
+
+        // The global flag overrides the arguments from the file, such that it enables free search for every solver
+        if program_args.ignore_search {
+            for solver in solvers.iter() {
+                if !solver.supported_std_flags().f {
+                    continue;
+                }
+                let args = solver_args.entry(solver.id().to_owned()).or_default();
+                if !args.iter().any(|arg| arg == "-f") {
+                    args.push("-f".to_owned());
+                }
             }
+        }
 
-            solver_args.insert(solver.id().to_owned(), args);
+        for (solver_name, flags) in &program_args.extra_fzn_flags {
+            solver_args
+                .entry(solver_name.clone())
+                .or_default()
+                .extend(flags.iter().cloned());
         }
 
-        Self {
-            memory_enforcer_interval: 3,
-            memory_threshold: 0.9,
+        let memory_threshold = file.memory_threshold.unwrap_or(DEFAULT_MEMORY_THRESHOLD);
+        Ok(Self {
+            memory_enforcer_interval: file.memory_enforcer_interval.unwrap_or(3),
+            memory_threshold,
+            // 0.75 for the default threshold of 0.9
+            memory_resume_threshold: file
+                .memory_resume_threshold
+                .unwrap_or(memory_threshold * 5.0 / 6.0),
+            memory_per_solver_bytes: program_args
+                .memory_per_solver
+                .or(file.memory_per_solver)
+                .map(|mib| mib_to_bytes("memory_per_solver", mib))
+                .transpose()?,
+            solver_memory_limits_bytes: file
+                .solver_memory_limits
+                .into_iter()
+                .map(|(solver_name, mib)| {
+                    let limit = mib_to_bytes(&format!("solver_memory_limits.{solver_name}"), mib)?;
+                    Ok((solver_name, limit))
+                })
+                .collect::<Result<_>>()?,
             solver_args,
+            expected_feature_length: file.expected_feature_length,
+            max_compilation_retries: file.max_compilation_retries.unwrap_or(2),
+            stall_timeout_secs: file.stall_timeout_secs,
+            restart_strategy: program_args
+                .restart_strategy
+                .or(file.restart_strategy)
+                .unwrap_or_default(),
+            max_solver_restarts: file.max_solver_restarts.unwrap_or(2),
+            solver_kill_grace_secs: file.solver_kill_grace_secs.unwrap_or(2),
+        })
+    }
+
+    /// Warns about the standard flags in the solver arguments that the solvers do not support.
+    pub fn validate_against_solvers(&self, solvers: &solver_config::Solvers) {
+        for (solver_name, args) in &self.solver_args {
+            let Some(solver) = solvers.get_by_id(solver_name) else {
+                continue;
+            };
+            let supported_flags = solver.supported_std_flags();
+
+            for arg in args {
+                let supported = match arg.as_str() {
+                    "-a" => supported_flags.a,
+                    "-i" => supported_flags.i,
+                    "-f" => supported_flags.f,
+                    "-p" => supported_flags.p,
+                    _ => continue,
+                };
+                if !supported {
+                    logging::warning!(
+                        "Solver '{solver_name}' does not support the standard flag '{arg}' given in its arguments"
+                    );
+                }
+            }
         }
     }
 }
+
+/// The memory limits are given in MiB, but enforced in bytes
+fn mib_to_bytes(name: &str, mib: u64) -> Result<u64> {
+    mib.checked_mul(1024 * 1024)
+        .ok_or_else(|| Error::Invalid(format!("{name} of {mib} MiB is too large")))
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("failed to read the config file '{path}'")]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("the config file is not valid TOML of the expected format")]
+    Toml(#[from] toml::de::Error),
+    #[error("invalid config: {0}")]
+    Invalid(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_per_solver_is_given_in_mib() {
+        let mut args = RunArgs::for_testing("model.mzn".into());
+        args.memory_per_solver = Some(2);
+        let config = Config::new(&args, &solver_config::Solvers::empty()).unwrap();
+        assert_eq!(config.memory_per_solver_bytes, Some(2 * 1024 * 1024));
+    }
+
+    #[test]
+    fn test_too_large_memory_per_solver_is_invalid() {
+        let mut args = RunArgs::for_testing("model.mzn".into());
+        args.memory_per_solver = Some(u64::MAX / 1024);
+        let result = Config::new(&args, &solver_config::Solvers::empty());
+        assert!(matches!(result, Err(Error::Invalid(_))), "{result:?}");
+    }
+
+    fn config_from_file(content: &str) -> Result<Config> {
+        let file = tempfile::Builder::new().suffix(".toml").tempfile().unwrap();
+        std::fs::write(file.path(), content).unwrap();
+        let mut args = RunArgs::for_testing("model.mzn".into());
+        args.config = Some(file.path().to_path_buf());
+        Config::new(&args, &solver_config::Solvers::empty())
+    }
+
+    #[test]
+    fn test_solver_memory_limits_are_given_in_mib() {
+        let config = config_from_file("[solver_memory_limits]\ngecode = 3\n").unwrap();
+        assert_eq!(config.solver_memory_limits_bytes["gecode"], 3 * 1024 * 1024);
+
+        let result = config_from_file(&format!("[solver_memory_limits]\ngecode = {}\n", i64::MAX));
+        assert!(matches!(result, Err(Error::Invalid(_))), "{result:?}");
+    }
+
+    #[test]
+    fn test_extra_fzn_flags_are_added_to_the_solver_args() {
+        let mut args = RunArgs::for_testing("model.mzn".into());
+        args.extra_fzn_flags = vec![("gecode".to_owned(), vec!["--restart".to_owned()])];
+        let config = Config::new(&args, &solver_config::Solvers::empty()).unwrap();
+        assert_eq!(config.solver_args["gecode"], ["--restart"]);
+    }
+}
@@ -0,0 +1,30 @@
+use crate::solver_output::Status;
+
+/// The exit codes that competition harnesses expect, derived from the final status of the solve
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitCode {
+    /// At least one solution was found, but the search did not finish
+    Solution = 0,
+    Unsatisfiable = 1,
+    /// Neither a solution nor a final status was found
+    Unknown = 2,
+    /// The search finished, e.g. with an optimal solution
+    Optimal = 3,
+    /// Parasol itself failed, e.g. because of an invalid argument
+    Error = 4,
+}
+
+impl ExitCode {
+    pub fn new(status: Option<&Status>, found_solution: bool) -> Self {
+        match status {
+            Some(Status::OptimalSolution | Status::AllSolutions) => Self::Optimal,
+            Some(Status::Unsatisfiable) => Self::Unsatisfiable,
+            Some(Status::Unbounded | Status::Unknown) | None if found_solution => Self::Solution,
+            Some(Status::Unbounded | Status::Unknown) | None => Self::Unknown,
+        }
+    }
+
+    pub fn code(self) -> i32 {
+        self as i32
+    }
+}
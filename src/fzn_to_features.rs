@@ -1,5 +1,6 @@
 use crate::ai::Features;
-use std::path::Path;
+use crate::logging;
+use std::path::{Path, PathBuf};
 use tokio::process::Command;
 
 #[derive(Debug, thiserror::Error)]
@@ -10,21 +11,83 @@ pub enum Error {
     FeatureParseFailed(String, #[source] std::num::ParseFloatError),
     #[error("IO error")]
     Io(#[from] tokio::io::Error),
+    #[error("the feature extractor '{}' was not found, check the --mzn2feat-exe flag", .0.display())]
+    ExecutableNotFound(PathBuf),
+    #[error("expected {expected} features but the feature extractor produced {actual}")]
+    UnexpectedFeatureLength { expected: usize, actual: usize },
 }
 
-pub async fn fzn_to_features(fzn_model: &Path) -> Result<Features, Error> {
-    let output: String = run_fzn_to_feat_cmd(fzn_model).await?;
-    output
+/// Extracts the features of the FlatZinc model, reusing the features of an earlier run on the same FlatZinc in
+/// `$XDG_CACHE_HOME/parasol/features` if `use_cache` is set
+pub async fn fzn_to_features(
+    fzn_model: &Path,
+    mzn2feat_exe: &Path,
+    expected_length: Option<usize>,
+    use_cache: bool,
+) -> Result<Features, Error> {
+    let cache_path = if use_cache {
+        match cache::entry_path(fzn_model, mzn2feat_exe).await {
+            Ok(path) => path,
+            Err(e) => {
+                logging::warning!("failed to read the FlatZinc for the feature cache: {e}");
+                None
+            }
+        }
+    } else {
+        None
+    };
+    // A cached vector of another length is left over from a different feature extractor, so it is replaced
+    if let Some(path) = &cache_path
+        && let Some(features) = cache::load(path).await
+        && expected_length.is_none_or(|expected| features.len() == expected)
+    {
+        logging::info!("using the cached features from {}", path.display());
+        return Ok(features);
+    }
+
+    let features = run_fzn_to_features(fzn_model, mzn2feat_exe, expected_length).await?;
+    if let Some(path) = &cache_path
+        && let Err(e) = cache::store(path, &features).await
+    {
+        logging::warning!("failed to store the features in the cache: {e}");
+    }
+    Ok(features)
+}
+
+async fn run_fzn_to_features(
+    fzn_model: &Path,
+    mzn2feat_exe: &Path,
+    expected_length: Option<usize>,
+) -> Result<Features, Error> {
+    let output: String = run_fzn_to_feat_cmd(fzn_model, mzn2feat_exe).await?;
+    let features = output
         .replace("\n", "")
         .split(",")
         .map(|s| s.parse::<f32>())
         .collect::<Result<Features, _>>()
-        .map_err(|e| Error::FeatureParseFailed(output, e))
+        .map_err(|e| Error::FeatureParseFailed(output, e))?;
+
+    if let Some(expected) = expected_length
+        && features.len() != expected
+    {
+        return Err(Error::UnexpectedFeatureLength {
+            expected,
+            actual: features.len(),
+        });
+    }
+
+    Ok(features)
 }
 
-async fn run_fzn_to_feat_cmd(fzn_model: &Path) -> Result<String, Error> {
-    let mut cmd = get_fzn_to_feat_cmd(fzn_model);
-    let output = cmd.output().await?;
+async fn run_fzn_to_feat_cmd(fzn_model: &Path, mzn2feat_exe: &Path) -> Result<String, Error> {
+    let mut cmd = get_fzn_to_feat_cmd(fzn_model, mzn2feat_exe);
+    let output = cmd.output().await.map_err(|e| {
+        if e.kind() == std::io::ErrorKind::NotFound {
+            Error::ExecutableNotFound(mzn2feat_exe.to_path_buf())
+        } else {
+            Error::Io(e)
+        }
+    })?;
     if !output.status.success() {
         return Err(Error::CommandFailed(output.status));
     }
@@ -32,11 +95,50 @@ async fn run_fzn_to_feat_cmd(fzn_model: &Path) -> Result<String, Error> {
     Ok(String::from_utf8_lossy(&output.stdout).to_string())
 }
 
-fn get_fzn_to_feat_cmd(fzn_model: &Path) -> Command {
-    let mut cmd = Command::new("mzn2feat");
+fn get_fzn_to_feat_cmd(fzn_model: &Path, mzn2feat_exe: &Path) -> Command {
+    let mut cmd = Command::new(mzn2feat_exe);
     cmd.kill_on_drop(true);
     cmd.arg("-i");
     cmd.arg(fzn_model);
 
     cmd
 }
+
+mod cache {
+    use std::path::{Path, PathBuf};
+
+    use sha2::{Digest, Sha256};
+
+    use crate::ai::Features;
+    use crate::storage;
+
+    /// The cache file for the features of the FlatZinc, keyed by the hash of its contents and the feature
+    /// extractor, since another extractor can produce different features of the same length
+    pub async fn entry_path(
+        fzn_model: &Path,
+        mzn2feat_exe: &Path,
+    ) -> std::io::Result<Option<PathBuf>> {
+        let Some(root) = storage::cache_dir("features") else {
+            return Ok(None);
+        };
+        let mut hasher = Sha256::new();
+        storage::update_part(&mut hasher, mzn2feat_exe.as_os_str().as_encoded_bytes());
+        storage::update_part(&mut hasher, &tokio::fs::read(fzn_model).await?);
+        Ok(Some(
+            root.join(format!("{}.json", storage::sha256_hex(hasher))),
+        ))
+    }
+
+    /// A missing or unreadable entry is a cache miss
+    pub async fn load(path: &Path) -> Option<Features> {
+        let content = tokio::fs::read(path).await.ok()?;
+        serde_json::from_slice(&content).ok()
+    }
+
+    pub async fn store(path: &Path, features: &Features) -> std::io::Result<()> {
+        if let Some(dir) = path.parent() {
+            tokio::fs::create_dir_all(dir).await?;
+        }
+        storage::write_atomic(path, &serde_json::to_vec(features)?).await
+    }
+}
@@ -1,3 +1,4 @@
+use super::BoundMode;
 use crate::model_parser::{ObjectiveType, ObjectiveValue};
 use async_tempfile::TempFile;
 use std::path::{Path, PathBuf};
@@ -9,6 +10,7 @@ pub async fn insert_objective(
     fzn_path: &Path,
     objective_type: &ObjectiveType,
     objective: ObjectiveValue,
+    bound_mode: BoundMode,
 ) -> Result<TempFile> {
     // NOTE: The FlatZinc grammar always ends with a "solve-item" and all statements end with a ';': https://docs.minizinc.dev/en/latest/fzn-spec.html#grammar
     let mut file = File::open(fzn_path)
@@ -74,7 +76,8 @@ pub async fn insert_objective(
         .next_back()
         .ok_or(Error::SplitReturnedEmptyIterator)?;
 
-    let objective_constraint = get_objective_constraint(objective_type, objective_name, objective)?;
+    let objective_constraint =
+        get_objective_constraint(objective_type, objective_name, objective, bound_mode)?;
 
     let uuid = Uuid::new_v4();
     let mut temp_file = TempFile::new_with_name(format!("temp-{uuid}.fzn")).await?;
@@ -99,14 +102,18 @@ fn get_objective_constraint(
     objective_type: &ObjectiveType,
     objective_name: &str,
     objective: ObjectiveValue,
+    bound_mode: BoundMode,
 ) -> Result<String> {
-    fn int_le(left: &str, right: &str) -> String {
-        format!("constraint int_le({left}, {right});")
-    }
+    let constraint = |left: &str, right: &str| {
+        format!(
+            "constraint {}({left}, {right});",
+            bound_mode.predicate(objective)
+        )
+    };
     match objective_type {
         ObjectiveType::Satisfy => Err(Error::GetObjectiveOnSatisfyType),
-        ObjectiveType::Minimize => Ok(int_le(objective_name, &objective.to_string())),
-        ObjectiveType::Maximize => Ok(int_le(&objective.to_string(), objective_name)),
+        ObjectiveType::Minimize => Ok(constraint(objective_name, &objective.to_string())),
+        ObjectiveType::Maximize => Ok(constraint(&objective.to_string(), objective_name)),
     }
 }
 
@@ -129,3 +136,83 @@ pub enum Error {
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn constraint(
+        objective_type: ObjectiveType,
+        bound_mode: BoundMode,
+        objective: ObjectiveValue,
+    ) -> String {
+        get_objective_constraint(&objective_type, "obj", objective, bound_mode).unwrap()
+    }
+
+    #[test]
+    fn test_int_bounds() {
+        let objective = ObjectiveValue::Int(10);
+        assert_eq!(
+            constraint(ObjectiveType::Minimize, BoundMode::Strict, objective),
+            "constraint int_lt(obj, 10);"
+        );
+        assert_eq!(
+            constraint(ObjectiveType::Minimize, BoundMode::NonStrict, objective),
+            "constraint int_le(obj, 10);"
+        );
+        assert_eq!(
+            constraint(ObjectiveType::Maximize, BoundMode::Strict, objective),
+            "constraint int_lt(10, obj);"
+        );
+        assert_eq!(
+            constraint(ObjectiveType::Maximize, BoundMode::NonStrict, objective),
+            "constraint int_le(10, obj);"
+        );
+    }
+
+    #[test]
+    fn test_float_bounds() {
+        let objective = ObjectiveValue::Float(2.5);
+        assert_eq!(
+            constraint(ObjectiveType::Minimize, BoundMode::Strict, objective),
+            "constraint float_lt(obj, 2.5);"
+        );
+        assert_eq!(
+            constraint(ObjectiveType::Minimize, BoundMode::NonStrict, objective),
+            "constraint float_le(obj, 2.5);"
+        );
+        assert_eq!(
+            constraint(ObjectiveType::Maximize, BoundMode::Strict, objective),
+            "constraint float_lt(2.5, obj);"
+        );
+        assert_eq!(
+            constraint(ObjectiveType::Maximize, BoundMode::NonStrict, objective),
+            "constraint float_le(2.5, obj);"
+        );
+    }
+
+    #[test]
+    fn test_whole_float_bound_is_a_float_literal() {
+        assert_eq!(
+            constraint(
+                ObjectiveType::Minimize,
+                BoundMode::NonStrict,
+                ObjectiveValue::Float(3.0)
+            ),
+            "constraint float_le(obj, 3.0);"
+        );
+    }
+
+    #[test]
+    fn test_satisfaction_has_no_bound() {
+        assert!(matches!(
+            get_objective_constraint(
+                &ObjectiveType::Satisfy,
+                "obj",
+                ObjectiveValue::Int(1),
+                BoundMode::Strict
+            ),
+            Err(Error::GetObjectiveOnSatisfyType)
+        ));
+    }
+}
@@ -1,3 +1,4 @@
+use super::BoundMode;
 use crate::model_parser::{ObjectiveType, ObjectiveValue};
 use async_tempfile::TempFile;
 use serde_json::json;
@@ -10,6 +11,7 @@ pub async fn insert_objective(
     json_path: &Path,
     objective_type: &ObjectiveType,
     objective: ObjectiveValue,
+    bound_mode: BoundMode,
 ) -> Result<TempFile> {
     let mut file = File::open(json_path)
         .await
@@ -25,8 +27,12 @@ pub async fn insert_objective(
 
     let objective_name = get_objective_name_from_json(objective_type, &json)?;
 
-    let constraint =
-        get_objective_constraint_json_value(objective_type, &objective_name, objective)?;
+    let constraint = get_objective_constraint_json_value(
+        objective_type,
+        &objective_name,
+        objective,
+        bound_mode,
+    )?;
 
     let constraints = json
         .get_mut("constraints")
@@ -77,13 +83,14 @@ fn get_objective_constraint_json_value(
     objective_type: &ObjectiveType,
     objective_name: &str,
     objective: ObjectiveValue,
+    bound_mode: BoundMode,
 ) -> Result<serde_json::Value> {
     let (left, right) = match objective_type {
         ObjectiveType::Satisfy => return Err(Error::GetObjectiveOnSatisfyType),
         ObjectiveType::Minimize => (json!(objective_name), json!(objective)),
         ObjectiveType::Maximize => (json!(objective), json!(objective_name)),
     };
-    Ok(json!({"id": "int_le", "args": [left, right]}))
+    Ok(json!({"id": bound_mode.predicate(objective), "args": [left, right]}))
 }
 
 #[derive(Debug, thiserror::Error)]
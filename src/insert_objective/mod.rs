@@ -8,13 +8,44 @@ use async_tempfile::TempFile;
 use std::path::Path;
 use std::sync::Arc;
 
+/// Whether the inserted bound allows solutions that are equal to the best objective
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BoundMode {
+    Strict,
+    NonStrict,
+}
+
+impl BoundMode {
+    pub fn new(strict: bool) -> Self {
+        if strict {
+            Self::Strict
+        } else {
+            Self::NonStrict
+        }
+    }
+
+    /// The FlatZinc comparison predicate for a bound of the domain of the objective
+    fn predicate(self, objective: ObjectiveValue) -> &'static str {
+        match (self, objective) {
+            (BoundMode::Strict, ObjectiveValue::Int(_)) => "int_lt",
+            (BoundMode::NonStrict, ObjectiveValue::Int(_)) => "int_le",
+            (BoundMode::Strict, ObjectiveValue::Float(_)) => "float_lt",
+            (BoundMode::NonStrict, ObjectiveValue::Float(_)) => "float_le",
+        }
+    }
+}
+
 pub struct ObjectiveInserter {
     solvers: Arc<solver_config::Solvers>,
+    bound_mode: BoundMode,
 }
 
 impl ObjectiveInserter {
-    pub fn new(solvers: Arc<solver_config::Solvers>) -> Self {
-        Self { solvers }
+    pub fn new(solvers: Arc<solver_config::Solvers>, bound_mode: BoundMode) -> Self {
+        Self {
+            solvers,
+            bound_mode,
+        }
     }
 
     pub async fn insert_objective(
@@ -36,12 +67,16 @@ impl ObjectiveInserter {
             });
 
         match input_type {
-            SolverInputType::Fzn => fzn::insert_objective(input_path, objective_type, objective)
-                .await
-                .map_err(Into::into),
-            SolverInputType::Json => json::insert_objective(input_path, objective_type, objective)
-                .await
-                .map_err(Into::into),
+            SolverInputType::Fzn => {
+                fzn::insert_objective(input_path, objective_type, objective, self.bound_mode)
+                    .await
+                    .map_err(Into::into)
+            }
+            SolverInputType::Json => {
+                json::insert_objective(input_path, objective_type, objective, self.bound_mode)
+                    .await
+                    .map_err(Into::into)
+            }
         }
     }
 }
@@ -1,10 +1,41 @@
-use crate::args::Verbosity;
-use std::sync::atomic::{AtomicU8, Ordering};
+use crate::args::{LogFormat, Verbosity};
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use std::time::SystemTime;
 
 static CURRENT_VERBOSITY: AtomicU8 = AtomicU8::new(LEVEL_WARNING);
+static JSON_FORMAT: AtomicBool = AtomicBool::new(false);
 
-pub fn init(verbosity: Verbosity) {
+pub fn init(verbosity: Verbosity, format: LogFormat) {
     CURRENT_VERBOSITY.store(verbosity as u8, Ordering::Relaxed);
+    JSON_FORMAT.store(format == LogFormat::Json, Ordering::Relaxed);
+}
+
+#[derive(Serialize)]
+struct JsonMessage<'a> {
+    level: String,
+    file: &'a str,
+    line: u32,
+    msg: String,
+    ts: u64,
+}
+
+fn format_text(level: &str, args: std::fmt::Arguments, file: &str, line: u32) -> String {
+    format!("{level}: [{file}:{line}] {args}")
+}
+
+fn format_json(level: &str, args: std::fmt::Arguments, file: &str, line: u32) -> String {
+    let ts = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |since_epoch| since_epoch.as_secs());
+    let message = JsonMessage {
+        level: level.to_lowercase(),
+        file,
+        line,
+        msg: args.to_string(),
+        ts,
+    };
+    serde_json::to_string(&message).unwrap_or_else(|_| format_text(level, args, file, line))
 }
 
 pub(crate) fn log_msg_impl(
@@ -17,7 +48,14 @@ pub(crate) fn log_msg_impl(
     let current_level = CURRENT_VERBOSITY.load(Ordering::Relaxed);
 
     if current_level >= verbosity {
-        eprintln!("{level}: [{file}:{line}] {args}");
+        // `file!()` can be an absolute path, e.g. when building outside of the workspace, so it is made crate relative
+        let file = file.rfind("src/").map_or(file, |i| &file[i..]);
+        let format = if JSON_FORMAT.load(Ordering::Relaxed) {
+            format_json
+        } else {
+            format_text
+        };
+        eprintln!("{}", format(level, args, file, line));
     }
 }
 
@@ -57,6 +95,19 @@ macro_rules! error_msg {
     };
 }
 
+/// An error that ends the program, which is printed even with the quiet verbosity
+macro_rules! fatal {
+    ($($arg:tt)*) => {
+        $crate::logging::log_msg_impl(
+            $crate::logging::LEVEL_QUIET,
+            "ERROR",
+            format_args!($($arg)*),
+            file!(),
+            line!()
+        )
+    };
+}
+
 // This function is purely used to force the anyhow::Error type
 // to avoid forgetting to convert it to that type before printing
 pub(crate) fn log_error_impl(e: &anyhow::Error, file: &str, line: u32) {
@@ -71,10 +122,10 @@ macro_rules! error {
 
 pub(crate) use error;
 pub(crate) use error_msg;
+pub(crate) use fatal;
 pub(crate) use info;
 pub(crate) use warning;
 
-#[allow(dead_code)]
 pub(crate) const LEVEL_QUIET: u8 = 0;
 pub(crate) const LEVEL_ERROR: u8 = 1;
 pub(crate) const LEVEL_WARNING: u8 = 2;
@@ -1,38 +1,59 @@
 mod ai;
 mod args;
 mod backup_solvers;
+mod benchmark;
+mod cgroup;
 mod config;
+mod exit_code;
 mod fzn_to_features;
 mod insert_objective;
 mod is_cancelled;
 mod logging;
+mod metrics;
+mod metrics_server;
 mod model_parser;
 mod mzn_to_fzn;
+mod portfolio_file;
 mod process_tree;
 mod scheduler;
 mod signal_handler;
+mod solution_writer;
 mod solver_config;
 mod solver_manager;
 mod solver_output;
 mod solvers;
 mod static_schedule;
+mod storage;
 mod sunny;
 
+use std::collections::HashMap;
+use std::path::Path;
 use std::process::exit;
 use std::sync::Arc;
+use std::time::Duration;
 
 use crate::ai::SimpleAi;
-use crate::args::{Ai, Cli, Command, RunArgs, parse_ai_config};
+use crate::args::{Ai, Cli, Command, Cores, RunArgs, parse_ai_config};
 use crate::backup_solvers::run_backup_solver;
 use crate::config::Config;
+use crate::exit_code::ExitCode;
 use crate::signal_handler::{SignalEvent, spawn_signal_handler};
+use crate::solution_writer::SolutionWriter;
 use crate::sunny::sunny;
 use clap::Parser;
 use tokio_util::sync::CancellationToken;
 
 #[tokio::main(flavor = "current_thread")]
 async fn main() {
-    let cli = Cli::parse();
+    let cli = Cli::try_parse().unwrap_or_else(|e| {
+        let _ = e.print();
+        // Usage errors must not be mistaken for a solve outcome, which clap's exit code 2 would be
+        exit(if e.use_stderr() {
+            ExitCode::Error.code()
+        } else {
+            0
+        });
+    });
 
     match cli.command {
         Command::BuildSolverCache(cache_args) => {
@@ -41,7 +62,7 @@ async fn main() {
                     .await
             {
                 logging::error_msg!("Failed to build solver cache: {e}");
-                exit(1);
+                exit(ExitCode::Error.code());
             }
         }
         Command::Run(args) => run(args).await,
@@ -53,21 +74,93 @@ async fn run(args: RunArgs) {
     let suspend_and_resume_signal_rx: tokio::sync::mpsc::UnboundedReceiver<SignalEvent> =
         spawn_signal_handler(program_cancellation_token.clone());
 
-    logging::init(args.verbosity);
+    logging::init(args.verbosity, args.log_format);
+
+    check_input_files_exist(&args);
+
+    if !args.no_fzn_cache {
+        mzn_to_fzn::disk_cache::prune_old_entries();
+    }
 
     let solvers = solver_config::load(&args.solver_config_mode, &args.minizinc.minizinc_exe).await;
 
-    let config = Config::new(&args, &solvers);
+    let config = match Config::new(&args, &solvers) {
+        Ok(config) => config,
+        Err(e) => {
+            logging::error!(e.into());
+            exit(ExitCode::Error.code());
+        }
+    };
+    config.validate_against_solvers(&solvers);
+    match serde_json::to_string(&config) {
+        Ok(config_json) => logging::info!("Config: {config_json}"),
+        Err(e) => logging::error!(e.into()),
+    }
+
+    let solution_writer = match SolutionWriter::new(&args).await {
+        Ok(solution_writer) => solution_writer,
+        Err(e) => {
+            logging::error_msg!("Failed to open the output file: {e}");
+            exit(ExitCode::Error.code());
+        }
+    };
 
-    let cores = args.cores;
+    let cores = args.cores();
+    if args.cores == Cores::Auto {
+        logging::info!("Detected {cores} available cores, using all of them");
+    }
+
+    if args.benchmark {
+        if let Err(e) = benchmark::benchmark(
+            &args,
+            config,
+            Arc::new(solvers),
+            solution_writer,
+            program_cancellation_token,
+            suspend_and_resume_signal_rx,
+        )
+        .await
+        {
+            logging::error!(e.into());
+            exit(ExitCode::Error.code());
+        }
+        return;
+    }
+
+    if args.ab_test_ai.is_some() {
+        if matches!(args.ai, Ai::None) {
+            logging::error_msg!("--ab-test-ai needs an AI to compare with, but --ai is none");
+            exit(ExitCode::Error.code());
+        }
+        if matches!(args.ai, Ai::Simple) {
+            // The simple AI has no configuration, so both sessions would run the same AI
+            logging::error_msg!(
+                "--ab-test-ai needs a configurable AI, but the simple AI has no configuration to compare"
+            );
+            exit(ExitCode::Error.code());
+        }
+        if args.ab_test_ai == args.ai_config {
+            logging::error_msg!(
+                "--ab-test-ai has the same configuration as --ai-config, so both sessions would run the same AI"
+            );
+            exit(ExitCode::Error.code());
+        }
+        if cores < 2 {
+            logging::error_msg!("--ab-test-ai needs at least 2 cores to split between the AIs");
+            exit(ExitCode::Error.code());
+        }
+    }
+    let ab_test_ai_config = args.ab_test_ai.as_deref();
 
     let result = match args.ai {
         Ai::None => {
             sunny(
                 &args,
                 None::<SimpleAi>,
+                None,
                 config,
                 Arc::new(solvers),
+                solution_writer.clone(),
                 program_cancellation_token.clone(),
                 suspend_and_resume_signal_rx,
             )
@@ -77,28 +170,36 @@ async fn run(args: RunArgs) {
             sunny(
                 &args,
                 Some(SimpleAi {}),
+                None,
                 config,
                 Arc::new(solvers),
+                solution_writer.clone(),
                 program_cancellation_token.clone(),
                 suspend_and_resume_signal_rx,
             )
             .await
         }
         Ai::CommandLine => {
-            let ai_config = parse_ai_config(args.ai_config.as_deref());
-            let Some(command) = ai_config.get("command") else {
-                logging::error_msg!(
-                    "'command' not provided in AI configuration when basic commandline AI has been specified"
-                );
-                exit(1);
-            };
-
-            let ai = crate::ai::commandline::Ai::new(command.clone(), args.verbosity);
             sunny(
                 &args,
-                Some(ai),
+                Some(commandline_ai(&args, args.ai_config.as_deref())),
+                ab_test_ai_config.map(|ai_config| commandline_ai(&args, Some(ai_config))),
+                config,
+                Arc::new(solvers),
+                solution_writer.clone(),
+                program_cancellation_token.clone(),
+                suspend_and_resume_signal_rx,
+            )
+            .await
+        }
+        Ai::Http => {
+            sunny(
+                &args,
+                Some(http_ai(args.ai_config.as_deref())),
+                ab_test_ai_config.map(|ai_config| http_ai(Some(ai_config))),
                 config,
                 Arc::new(solvers),
+                solution_writer.clone(),
                 program_cancellation_token.clone(),
                 suspend_and_resume_signal_rx,
             )
@@ -107,22 +208,111 @@ async fn run(args: RunArgs) {
     };
 
     match result {
-        Ok(()) => {}
+        // Only the dry run finishes without solving
+        Ok(()) => return,
         Err(sunny::Error::Cancelled) => {
             // User cancelled, don't run backup solver
         }
+        Err(e @ sunny::Error::TimeLimitWithoutSolution) => {
+            logging::error!(e.into());
+        }
+        Err(
+            e @ (sunny::Error::EmptyFilteredPortfolio
+            | sunny::Error::Schedule(static_schedule::Error::UnknownSolvers(_))),
+        ) => {
+            logging::error!(e.into());
+            exit(ExitCode::Error.code());
+        }
         Err(e) => {
             logging::error!(e.into());
             logging::error_msg!("Portfolio solver failed, falling back to backup solver");
             tokio::select! {
                 _ = program_cancellation_token.cancelled() => {},
-                result = run_backup_solver(&args, cores) => {
+                result = run_backup_solver(&args, cores, &solution_writer) => {
                     if let Err(e) = result {
                         logging::error!(e.into());
-                        exit(1);
+                        exit(ExitCode::Error.code());
                     }
                 }
             }
         }
     }
+    exit(solution_writer.exit_code().code());
+}
+
+fn commandline_ai(args: &RunArgs, ai_config: Option<&str>) -> crate::ai::commandline::Ai {
+    let ai_config = load_ai_config(ai_config);
+    let Some(command) = ai_config.get("command") else {
+        logging::error_msg!(
+            "'command' not provided in AI configuration when basic commandline AI has been specified"
+        );
+        exit(ExitCode::Error.code());
+    };
+    let timeout = parse_ai_timeout(&ai_config, crate::ai::commandline::DEFAULT_TIMEOUT);
+
+    crate::ai::commandline::Ai::new(command.clone(), args.verbosity, timeout)
+}
+
+fn http_ai(ai_config: Option<&str>) -> crate::ai::http::Ai {
+    let ai_config = load_ai_config(ai_config);
+    let Some(url) = ai_config.get("url") else {
+        logging::error_msg!(
+            "'url' not provided in AI configuration when the HTTP AI has been specified"
+        );
+        exit(ExitCode::Error.code());
+    };
+    let timeout = parse_ai_timeout(&ai_config, crate::ai::http::DEFAULT_TIMEOUT);
+
+    match crate::ai::http::Ai::new(url.clone(), timeout) {
+        Ok(ai) => ai,
+        Err(e) => {
+            logging::error!(e.into());
+            exit(ExitCode::Error.code());
+        }
+    }
+}
+
+fn load_ai_config(ai_config: Option<&str>) -> HashMap<String, String> {
+    match parse_ai_config(ai_config) {
+        Ok(ai_config) => ai_config,
+        Err(e) => {
+            logging::error_msg!("{e}");
+            exit(ExitCode::Error.code());
+        }
+    }
+}
+
+fn parse_ai_timeout(ai_config: &HashMap<String, String>, default: Duration) -> Duration {
+    match ai_config
+        .get("timeout")
+        .map(|timeout| timeout.parse::<u64>())
+    {
+        None => default,
+        Some(Ok(seconds)) => Duration::from_secs(seconds),
+        Some(Err(_)) => {
+            logging::error_msg!(
+                "'timeout' in the AI configuration must be a whole number of seconds"
+            );
+            exit(ExitCode::Error.code());
+        }
+    }
+}
+
+fn check_input_files_exist(args: &RunArgs) {
+    let files: [(&str, Option<&Path>); 4] = [
+        ("Model", Some(&args.model)),
+        ("Static schedule", args.static_schedule.as_deref()),
+        ("Timeout schedule", args.timeout_schedule.as_deref()),
+        ("Config", args.config.as_deref()),
+    ];
+    let data_files = args.data_files().map(|path| ("Data", Some(path)));
+
+    for (kind, path) in files.into_iter().chain(data_files) {
+        if let Some(path) = path
+            && !path.try_exists().unwrap_or(false)
+        {
+            logging::fatal!("{kind} file not found: {}", path.display());
+            exit(ExitCode::Error.code());
+        }
+    }
 }
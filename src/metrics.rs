@@ -0,0 +1,97 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::args::RunArgs;
+use crate::model_parser::{ObjectiveType, ObjectiveValue};
+use crate::storage;
+
+/// What a solver contributed to the runs on a problem instance
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SolverMetrics {
+    /// The number of solutions that improved the objective of the solver
+    pub improving_solutions: u64,
+    /// The best objective the solver found itself
+    pub best_objective: Option<ObjectiveValue>,
+    /// The number of solutions the solver found, including the ones that did not improve its objective
+    #[serde(default)]
+    pub solution_count: u64,
+    /// The seconds from the start of the run until the first solution of the solver
+    #[serde(default)]
+    pub time_to_first_solution: Option<f64>,
+}
+
+/// The metrics per solver name
+pub type Metrics = HashMap<String, SolverMetrics>;
+
+impl SolverMetrics {
+    /// Adds the metrics of another run, keeping the better of both objectives and the fastest first solution
+    pub fn merge(&mut self, other: &SolverMetrics, objective_type: ObjectiveType) {
+        self.improving_solutions += other.improving_solutions;
+        self.solution_count += other.solution_count;
+        self.time_to_first_solution =
+            match (self.time_to_first_solution, other.time_to_first_solution) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            };
+        if let Some(objective) = other.best_objective
+            && objective_type.is_better(self.best_objective, objective)
+        {
+            self.best_objective = Some(objective);
+        }
+    }
+}
+
+/// The key of the metrics of a solver, which is prefixed with the AI session that scheduled it in the A/B test mode
+pub fn key(solver_name: &str, session: Option<&str>) -> String {
+    match session {
+        Some(session) => format!("{session}/{solver_name}"),
+        None => solver_name.to_owned(),
+    }
+}
+
+/// The metrics file of the problem instance, named after the hash of the contents of the model and data files
+pub async fn path(args: &RunArgs) -> Result<Option<PathBuf>> {
+    let Some(root) = storage::data_dir("metrics") else {
+        return Ok(None);
+    };
+
+    let mut hasher = Sha256::new();
+    storage::update_part(&mut hasher, &tokio::fs::read(&args.model).await?);
+    for data in args.data_files() {
+        storage::update_part(&mut hasher, &tokio::fs::read(data).await?);
+    }
+    let hash = storage::sha256_hex(hasher);
+
+    Ok(Some(root.join(format!("{hash}.json"))))
+}
+
+/// Loads the metrics of the previous runs, which are empty if there were none
+pub async fn load(path: &Path) -> Result<Metrics> {
+    match tokio::fs::read_to_string(path).await {
+        Ok(content) => Ok(serde_json::from_str(&content)?),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Metrics::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn save(path: &Path, metrics: &Metrics) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        tokio::fs::create_dir_all(dir).await?;
+    }
+
+    storage::write_atomic(path, &serde_json::to_vec_pretty(metrics)?).await?;
+    Ok(())
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("IO error")]
+    Io(#[from] std::io::Error),
+    #[error("the metrics file is not valid JSON of the expected format")]
+    Json(#[from] serde_json::Error),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
@@ -0,0 +1,121 @@
+use std::convert::Infallible;
+use std::fmt::Write;
+use std::future::Future;
+
+use http_body_util::Full;
+use hyper::body::Bytes;
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Method, Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use tokio::net::TcpListener;
+use tokio_util::sync::CancellationToken;
+
+use crate::logging;
+
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+/// Serves the metrics rendered by `render` on `GET /metrics` on localhost, until the cancellation
+pub async fn serve<F, Fut>(
+    port: u16,
+    render: F,
+    cancellation_token: CancellationToken,
+) -> std::io::Result<()>
+where
+    F: Fn() -> Fut + Clone + Send + 'static,
+    Fut: Future<Output = String> + Send,
+{
+    let listener = TcpListener::bind(("127.0.0.1", port)).await?;
+    logging::info!("serving the metrics on http://127.0.0.1:{port}/metrics");
+
+    loop {
+        let stream = tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    logging::warning!("failed to accept a metrics connection: {e}");
+                    continue;
+                }
+            },
+            _ = cancellation_token.cancelled() => return Ok(()),
+        };
+
+        let render = render.clone();
+        let cancellation_token = cancellation_token.clone();
+        tokio::spawn(async move {
+            let service = service_fn(move |request| respond(request, render.clone()));
+            let connection = http1::Builder::new().serve_connection(TokioIo::new(stream), service);
+            tokio::select! {
+                result = connection => {
+                    if let Err(e) = result {
+                        logging::warning!("failed to serve the metrics: {e}");
+                    }
+                }
+                _ = cancellation_token.cancelled() => {}
+            }
+        });
+    }
+}
+
+async fn respond<F, Fut>(
+    request: Request<hyper::body::Incoming>,
+    render: F,
+) -> Result<Response<Full<Bytes>>, Infallible>
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = String>,
+{
+    let response = if request.method() == Method::GET && request.uri().path() == "/metrics" {
+        Response::builder()
+            .header(hyper::header::CONTENT_TYPE, CONTENT_TYPE)
+            .body(Full::new(Bytes::from(render().await)))
+    } else {
+        Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body(Full::new(Bytes::from_static(b"not found\n")))
+    };
+    Ok(response.expect("the response is built from valid parts"))
+}
+
+/// Builds the metrics in the Prometheus text exposition format
+#[derive(Default)]
+pub struct Exposition(String);
+
+impl Exposition {
+    /// Adds a metric without labels, or with the given label values for each sample
+    pub fn metric<'a>(
+        &mut self,
+        name: &str,
+        kind: &str,
+        help: &str,
+        samples: impl IntoIterator<Item = (Option<(&'a str, &'a str)>, String)>,
+    ) {
+        let _ = writeln!(self.0, "# HELP {name} {help}");
+        let _ = writeln!(self.0, "# TYPE {name} {kind}");
+        for (label, value) in samples {
+            match label {
+                Some((key, label_value)) => {
+                    let _ = writeln!(
+                        self.0,
+                        "{name}{{{key}=\"{}\"}} {value}",
+                        escape_label_value(label_value)
+                    );
+                }
+                None => {
+                    let _ = writeln!(self.0, "{name} {value}");
+                }
+            }
+        }
+    }
+
+    pub fn into_string(self) -> String {
+        self.0
+    }
+}
+
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
@@ -1,8 +1,92 @@
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::hash::{Hash, Hasher};
 use std::path::Path;
 use std::process::ExitStatus;
 use tokio::process::Command;
 
-pub type ObjectiveValue = i64;
+/// The value of the objective, in the domain of the objective variable of the model.
+/// Parsed floats are always finite and never `-0.0`, such that equality and hashing agree with the ordering.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ObjectiveValue {
+    Int(i64),
+    Float(f64),
+}
+
+/// The type of the objective variable, as reported by the model interface
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ObjectiveDomain {
+    #[default]
+    Int,
+    Float,
+}
+
+impl ObjectiveValue {
+    pub fn parse(value: &str, domain: ObjectiveDomain) -> Option<Self> {
+        match domain {
+            ObjectiveDomain::Int => value.parse().ok().map(Self::Int),
+            ObjectiveDomain::Float => value.parse().ok().and_then(Self::float),
+        }
+    }
+
+    pub fn from_json(value: &serde_json::Value, domain: ObjectiveDomain) -> Option<Self> {
+        match domain {
+            ObjectiveDomain::Int => value.as_i64().map(Self::Int),
+            ObjectiveDomain::Float => value.as_f64().and_then(Self::float),
+        }
+    }
+
+    /// Rejects the infinite and NaN values, which have no FlatZinc literal and cannot be ordered, and turns
+    /// `-0.0` into `0.0`
+    fn float(value: f64) -> Option<Self> {
+        value.is_finite().then_some(Self::Float(value + 0.0))
+    }
+}
+
+impl std::fmt::Display for ObjectiveValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Int(value) => write!(f, "{value}"),
+            // The debug format always includes a decimal point or exponent, as FlatZinc float literals require
+            Self::Float(value) => write!(f, "{value:?}"),
+        }
+    }
+}
+
+impl PartialEq for ObjectiveValue {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Int(a), Self::Int(b)) => a == b,
+            (Self::Float(a), Self::Float(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for ObjectiveValue {}
+
+impl Hash for ObjectiveValue {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match self {
+            Self::Int(value) => value.hash(state),
+            // `0.0` and `-0.0` are equal, so they must hash the same
+            Self::Float(value) => (value + 0.0).to_bits().hash(state),
+        }
+    }
+}
+
+impl PartialOrd for ObjectiveValue {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match (self, other) {
+            (Self::Int(a), Self::Int(b)) => Some(a.cmp(b)),
+            (Self::Float(a), Self::Float(b)) => a.partial_cmp(b),
+            // The values of an objective are always in the same domain, and values of different domains are
+            // never equal either
+            _ => None,
+        }
+    }
+}
 
 #[derive(Debug, thiserror::Error)]
 pub enum ModelParseError {
@@ -44,10 +128,11 @@ impl ObjectiveType {
     }
 }
 
+/// Returns the objective type and the domain of the objective variable of the model
 pub async fn get_objective_type(
     minizinc_command: &Path,
     model_path: &Path,
-) -> Result<ObjectiveType, ModelParseError> {
+) -> Result<(ObjectiveType, ObjectiveDomain), ModelParseError> {
     let output = run_model_interface_cmd(minizinc_command, model_path).await?;
     let json: serde_json::Value =
         serde_json::from_str(&output).map_err(|_| CommandOutputError::NonJsonOutput(output))?;
@@ -55,11 +140,28 @@ pub async fn get_objective_type(
         return Err(CommandOutputError::JsonIsNotObject(json.to_string()).into());
     };
 
-    parse_method_from_json_object(object)
+    let objective_type = parse_method_from_json_object(&object)?;
+    Ok((objective_type, parse_domain_from_json_object(&object)))
+}
+
+/// The model interface lists `_objective` among the output variables, together with its type.
+/// Integer is assumed if it is missing, which is also the case for satisfaction problems.
+fn parse_domain_from_json_object(
+    object: &serde_json::Map<String, serde_json::Value>,
+) -> ObjectiveDomain {
+    match object
+        .get("output")
+        .and_then(|output| output.get("_objective"))
+        .and_then(|objective| objective.get("type"))
+        .and_then(|objective_type| objective_type.as_str())
+    {
+        Some("float") => ObjectiveDomain::Float,
+        _ => ObjectiveDomain::Int,
+    }
 }
 
 fn parse_method_from_json_object(
-    object: serde_json::Map<String, serde_json::Value>,
+    object: &serde_json::Map<String, serde_json::Value>,
 ) -> Result<ObjectiveType, ModelParseError> {
     let Some(method_json) = object.get("method") else {
         return Err(ModelParseError::MethodParseError(
@@ -105,3 +207,67 @@ fn get_model_interface_cmd(minizinc_command: &Path, model_path: &Path) -> Comman
 
     cmd
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            ObjectiveValue::parse("42", ObjectiveDomain::Int),
+            Some(ObjectiveValue::Int(42))
+        );
+        assert_eq!(ObjectiveValue::parse("4.5", ObjectiveDomain::Int), None);
+        assert_eq!(
+            ObjectiveValue::parse("4.5", ObjectiveDomain::Float),
+            Some(ObjectiveValue::Float(4.5))
+        );
+        assert_eq!(
+            ObjectiveValue::parse("3", ObjectiveDomain::Float),
+            Some(ObjectiveValue::Float(3.0))
+        );
+    }
+
+    #[test]
+    fn test_parse_rejects_non_finite_floats() {
+        for value in ["inf", "-inf", "infinity", "NaN"] {
+            assert_eq!(ObjectiveValue::parse(value, ObjectiveDomain::Float), None);
+        }
+    }
+
+    #[test]
+    fn test_negative_zero_equals_zero() {
+        let negative_zero = ObjectiveValue::parse("-0.0", ObjectiveDomain::Float).unwrap();
+        let zero = ObjectiveValue::Float(0.0);
+        assert_eq!(negative_zero, zero);
+        assert_eq!(negative_zero.partial_cmp(&zero), Some(Ordering::Equal));
+        assert_eq!(HashSet::from([negative_zero, zero]).len(), 1);
+        assert_eq!(negative_zero.to_string(), "0.0");
+    }
+
+    #[test]
+    fn test_float_display_is_a_flatzinc_literal() {
+        assert_eq!(ObjectiveValue::Float(2.0).to_string(), "2.0");
+        assert_eq!(ObjectiveValue::Float(-2.5).to_string(), "-2.5");
+        assert_eq!(ObjectiveValue::Float(1e300).to_string(), "1e300");
+    }
+
+    #[test]
+    fn test_is_better() {
+        let int = ObjectiveValue::Int;
+        assert!(ObjectiveType::Minimize.is_better(None, int(5)));
+        assert!(ObjectiveType::Minimize.is_better(Some(int(5)), int(4)));
+        assert!(!ObjectiveType::Minimize.is_better(Some(int(5)), int(5)));
+        assert!(!ObjectiveType::Minimize.is_better(Some(int(5)), int(6)));
+        assert!(ObjectiveType::Maximize.is_better(Some(int(5)), int(6)));
+        assert!(!ObjectiveType::Maximize.is_better(Some(int(5)), int(5)));
+        assert!(!ObjectiveType::Maximize.is_better(Some(int(5)), int(4)));
+
+        let float = ObjectiveValue::Float;
+        assert!(ObjectiveType::Minimize.is_better(Some(float(0.5)), float(0.25)));
+        assert!(!ObjectiveType::Minimize.is_better(Some(float(0.0)), float(0.0)));
+        assert!(ObjectiveType::Maximize.is_better(Some(float(-1.5)), float(-1.0)));
+    }
+}
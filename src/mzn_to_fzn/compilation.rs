@@ -1,16 +1,23 @@
-use super::Conversion;
+use super::{Conversion, disk_cache};
 use crate::args::RunArgs;
 use crate::is_cancelled::IsCancelled;
 use crate::logging;
+use crate::solver_config::Solver;
 use std::path::Path;
 use std::process::Stdio;
+use std::time::Duration;
 use tokio::io::{AsyncBufReadExt, BufReader};
 use tokio::process::Command;
 use tokio_util::sync::CancellationToken;
 
+/// The delay before the first retry of a compilation, which is doubled for every following retry
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(100);
+
 pub async fn convert_mzn(
     args: &RunArgs,
     solver_name: &str,
+    solver: Option<&Solver>,
+    max_retries: u32,
     cancellation_token: CancellationToken,
 ) -> Result<Conversion> {
     let fzn_file = tempfile::Builder::new()
@@ -22,18 +29,81 @@ pub async fn convert_mzn(
         .tempfile()
         .map_err(ConversionError::TempFile)?;
 
-    run_mzn_to_fzn_cmd(
-        args,
-        solver_name,
-        fzn_file.path(),
-        ozn_file.path(),
-        cancellation_token,
-    )
-    .await?;
+    let cache_entry_dir = if args.no_fzn_cache {
+        None
+    } else {
+        disk_cache::entry_dir(args, solver_name, solver)
+            .await
+            .unwrap_or_else(|e| {
+                logging::warning!("failed to determine the FlatZinc cache entry: {e}");
+                None
+            })
+    };
+
+    if let Some(entry_dir) = &cache_entry_dir {
+        match disk_cache::load(entry_dir, fzn_file.path(), ozn_file.path()).await {
+            Ok(true) => {
+                logging::info!("using the cached compilation for solver '{solver_name}'");
+                return Ok(Conversion { fzn_file, ozn_file });
+            }
+            Ok(false) => {}
+            Err(e) => logging::warning!("failed to load the cached compilation: {e}"),
+        }
+    }
+
+    let mut retry = 0;
+    loop {
+        let result = run_mzn_to_fzn_cmd(
+            args,
+            solver_name,
+            fzn_file.path(),
+            ozn_file.path(),
+            cancellation_token.clone(),
+        )
+        .await;
+
+        match result {
+            Err(Error::Conversion(ConversionError::Io(e)))
+                if retry < max_retries && is_transient(&e) =>
+            {
+                retry += 1;
+                let delay = retry_delay(retry);
+                logging::warning!(
+                    "compilation for solver '{solver_name}' failed with a transient IO error: {e}. Retry {retry} in {delay:?}"
+                );
+                tokio::select! {
+                    _ = tokio::time::sleep(delay) => {}
+                    _ = cancellation_token.cancelled() => return Err(Error::Cancelled(solver_name.to_owned())),
+                }
+            }
+            result => break result?,
+        }
+    }
+
+    if let Some(entry_dir) = &cache_entry_dir
+        && let Err(e) = disk_cache::store(entry_dir, fzn_file.path(), ozn_file.path()).await
+    {
+        logging::warning!("failed to store the compilation in the cache: {e}");
+    }
 
     Ok(Conversion { fzn_file, ozn_file })
 }
 
+/// The delay before the given retry, starting at 1
+fn retry_delay(retry: u32) -> Duration {
+    RETRY_BASE_DELAY * 2u32.pow(retry - 1)
+}
+
+/// Whether the IO error might not happen again, e.g. when the machine is temporarily overloaded
+fn is_transient(e: &std::io::Error) -> bool {
+    matches!(
+        e.kind(),
+        std::io::ErrorKind::WouldBlock
+            | std::io::ErrorKind::Interrupted
+            | std::io::ErrorKind::ResourceBusy
+    )
+}
+
 async fn run_mzn_to_fzn_cmd(
     args: &RunArgs,
     solver_name: &str,
@@ -86,9 +156,7 @@ fn get_mzn_to_fzn_cmd(
     cmd.process_group(0);
     cmd.arg("-c");
     cmd.arg(&args.model);
-    if let Some(data) = &args.data {
-        cmd.arg(data);
-    }
+    cmd.args(args.data_files());
     cmd.args(["--solver", solver_name]);
     cmd.arg("-o").arg(fzn_result_path);
     cmd.arg("--output-objective");
@@ -138,3 +206,30 @@ impl<T> IsCancelled for Result<T> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::ErrorKind;
+
+    #[test]
+    fn test_retry_delay_doubles() {
+        assert_eq!(retry_delay(1), RETRY_BASE_DELAY);
+        assert_eq!(retry_delay(2), RETRY_BASE_DELAY * 2);
+        assert_eq!(retry_delay(3), RETRY_BASE_DELAY * 4);
+    }
+
+    #[test]
+    fn test_only_transient_errors_are_retried() {
+        for kind in [
+            ErrorKind::WouldBlock,
+            ErrorKind::Interrupted,
+            ErrorKind::ResourceBusy,
+        ] {
+            assert!(is_transient(&std::io::Error::from(kind)), "{kind:?}");
+        }
+        for kind in [ErrorKind::NotFound, ErrorKind::PermissionDenied] {
+            assert!(!is_transient(&std::io::Error::from(kind)), "{kind:?}");
+        }
+    }
+}
@@ -1,9 +1,12 @@
-use std::collections::hash_map::Entry;
+use std::collections::hash_map::{DefaultHasher, Entry};
+use std::hash::{Hash, Hasher};
+use std::path::Path;
 use std::{
     collections::{HashMap, HashSet},
     sync::Arc,
 };
 
+use itertools::Itertools;
 use tokio::sync::RwLock;
 use tokio::sync::watch;
 use tokio::sync::watch::Receiver;
@@ -14,12 +17,20 @@ use super::Conversion;
 use super::compilation;
 use crate::args::RunArgs;
 use crate::is_cancelled::{IsCancelled, IsErrorCancelled};
-use crate::logging;
+use crate::{logging, solver_config};
+
+/// Identifies a compilation by the model, the data and the solver, such that compilations of different
+/// instances never get mixed up.
+type CacheKey = String;
 
 pub struct CompilationManager {
     args: Arc<RunArgs>,
+    solvers: Arc<solver_config::Solvers>,
+    max_retries: u32,
+    /// The part of the cache key that identifies the model and data files
+    instance_key: String,
     /// Invariant that needs to be upheld: If a started compilation is cancelled, it also needs to be removed.
-    compilations: Arc<RwLock<HashMap<String, Compilation>>>,
+    compilations: Arc<RwLock<HashMap<CacheKey, Compilation>>>,
     /// The cancellation token for the manager itself.
     /// If cancelled, the manager will stop working as intended, but it can be used to cancel all
     /// running processes at once.
@@ -39,79 +50,130 @@ struct RunningCompilation {
 }
 
 impl CompilationManager {
-    pub fn new(args: Arc<RunArgs>) -> Self {
+    pub fn new(args: Arc<RunArgs>, solvers: Arc<solver_config::Solvers>, max_retries: u32) -> Self {
+        let instance_key = std::iter::once(args.model.as_path())
+            .chain(args.data_files())
+            .map(|path| format!("{:x}", file_hash(path)))
+            .join(":");
         Self {
             args,
+            solvers,
+            max_retries,
+            instance_key,
             cancellation_token: CancellationToken::new(),
             compilations: Default::default(),
         }
     }
 
+    fn cache_key(&self, solver_name: &str) -> CacheKey {
+        format!("{}:{solver_name}", self.instance_key)
+    }
+
     pub async fn start(&self, solver_name: String) {
         self.start_many([solver_name].into_iter()).await
     }
 
+    /// Starts a compilation for each of the given solvers that does not already have one.
     pub async fn start_many(&self, solver_names: impl Iterator<Item = String>) {
-        let mut compilations = self.compilations.write().await;
-        let new_solvers = solver_names.filter(|name| !compilations.contains_key(name));
+        let new_solvers: Vec<String> = {
+            let compilations = self.compilations.read().await;
+            solver_names
+                .unique()
+                .filter(|name| !compilations.contains_key(&self.cache_key(name)))
+                .collect()
+        };
 
+        // The compilations are spawned without holding the lock, so they are only inserted afterwards.
+        // A compilation that finishes before it is inserted has already stored its result, which is kept.
+        // If another call started the same compilation in the meantime, the new one is cancelled again.
+        // The spawned tasks own the sending half of the watch channel, so if a task panics, the channel is
+        // closed and waiting for the compilation fails instead of hanging.
         let new_compilations: Vec<_> = new_solvers
-            .map(|solver_name| {
-                let cancellation_token = self.cancellation_token.child_token();
-                let args = self.args.clone();
-                let cancellation_token_clone = cancellation_token.clone();
-                let name_clone = solver_name.clone();
-
-                let compilations = self.compilations.clone();
-
-                let (tx, rx) = watch::channel(None);
-
-                tokio::spawn(async move {
-                    logging::info!("starting compilation for solver '{solver_name}'");
-                    let compilation =
-                        compilation::convert_mzn(&args, &solver_name, cancellation_token_clone)
-                            .await
-                            .map_err(|e| {
-                                let error = WaitForError::from(&e);
-                                logging::error!(e.into());
-                                error
-                            })
-                            .map(Arc::new);
-
-                    if !compilation.is_error_cancelled() {
-                        compilations
-                            .write()
-                            .await
-                            .insert(solver_name.clone(), Compilation::Done(compilation.clone()));
-                    }
-                    // NOTE: If the compilation is cancelled, we do not here remove the started compilation from the
-                    //       self.compilations map, because the only way the compilation gets cancelled is in stop_all,
-                    //       which also removes it from the map.
-
-                    let _ = tx.send(Some(compilation)).map_err(|e| {
-                        logging::error!(Error::SendError(solver_name.clone(), e).into())
-                    });
-                    logging::info!("Compilation for solver '{solver_name}' is done");
-                });
-
-                (
-                    name_clone,
-                    RunningCompilation {
-                        cancellation_token,
-                        receiver: rx,
-                    },
-                )
-            })
+            .into_iter()
+            .map(|solver_name| self.spawn_compilation(solver_name))
             .collect();
 
-        for (name, compilation) in new_compilations {
-            compilations.insert(name, Compilation::Running(Arc::new(compilation)));
+        let mut compilations = self.compilations.write().await;
+        for (key, compilation) in new_compilations {
+            match compilations.entry(key) {
+                Entry::Vacant(entry) => {
+                    entry.insert(Compilation::Running(Arc::new(compilation)));
+                }
+                Entry::Occupied(_) => compilation.cancellation_token.cancel(),
+            }
         }
     }
 
+    fn spawn_compilation(&self, solver_name: String) -> (CacheKey, RunningCompilation) {
+        let cancellation_token = self.cancellation_token.child_token();
+        let args = self.args.clone();
+        let solvers = self.solvers.clone();
+        let max_retries = self.max_retries;
+        let cancellation_token_clone = cancellation_token.clone();
+        let key = self.cache_key(&solver_name);
+        let key_clone = key.clone();
+
+        let compilations = self.compilations.clone();
+
+        let (tx, rx) = watch::channel(None);
+
+        tokio::spawn(async move {
+            logging::info!("starting compilation for solver '{solver_name}'");
+            let compilation = compilation::convert_mzn(
+                &args,
+                &solver_name,
+                solvers.get_by_id(&solver_name),
+                max_retries,
+                cancellation_token_clone,
+            )
+            .await
+            .map_err(|e| {
+                let error = WaitForError::from(&e);
+                logging::error!(e.into());
+                error
+            })
+            .map(Arc::new);
+
+            if let Ok(conversion) = &compilation
+                && let (Ok(fzn_size), Ok(ozn_size)) = (
+                    conversion.fzn_size_bytes().await,
+                    conversion.ozn_size_bytes().await,
+                )
+            {
+                logging::info!(
+                    "Compilation for solver '{solver_name}' produced {fzn_size} bytes of FlatZinc and {ozn_size} bytes of ozn"
+                );
+            }
+
+            if !compilation.is_error_cancelled() {
+                compilations
+                    .write()
+                    .await
+                    .insert(key_clone, Compilation::Done(compilation.clone()));
+            }
+            // NOTE: If the compilation is cancelled, we do not here remove the started compilation from the
+            //       self.compilations map, because the only way the compilation gets cancelled is in stop_all,
+            //       which also removes it from the map, or in start_many, where it was never inserted.
+
+            let _ = tx
+                .send(Some(compilation))
+                .map_err(|e| logging::error!(Error::SendError(solver_name.clone(), e).into()));
+            logging::info!("Compilation for solver '{solver_name}' is done");
+        });
+
+        (
+            key,
+            RunningCompilation {
+                cancellation_token,
+                receiver: rx,
+            },
+        )
+    }
+
     /// Cancellation safe
     pub async fn wait_for(&self, solver_name: &str) -> WaitForResult {
-        let compilation = { self.compilations.read().await.get(solver_name).cloned() };
+        let key = self.cache_key(solver_name);
+        let compilation = { self.compilations.read().await.get(&key).cloned() };
 
         let Some(compilation) = compilation else {
             return Err(WaitForError::NotStarted(solver_name.to_string()));
@@ -138,16 +200,22 @@ impl CompilationManager {
         }
     }
 
+    #[allow(dead_code)]
     pub async fn stop_many(&self, solver_names: impl Iterator<Item = String>) {
+        let keys = solver_names.map(|name| self.cache_key(&name));
+        self.stop_many_by_key(keys).await
+    }
+
+    async fn stop_many_by_key(&self, keys: impl Iterator<Item = CacheKey>) {
         let mut compilations = self.compilations.write().await;
 
-        for solver_name in solver_names {
-            if let Entry::Occupied(compilation) = compilations.entry(solver_name) {
+        for key in keys {
+            if let Entry::Occupied(compilation) = compilations.entry(key) {
                 match compilation.get() {
                     Compilation::Running(started_compilation) => {
                         started_compilation.cancellation_token.cancel();
-                        let (solver_name, _) = compilation.remove_entry();
-                        logging::info!("stopped the compilation for solver '{solver_name}'");
+                        let (key, _) = compilation.remove_entry();
+                        logging::info!("stopped the compilation '{key}'");
                     }
                     Compilation::Done(_) => {
                         logging::info!("attempted to stop a finished compilation for a solver");
@@ -163,21 +231,35 @@ impl CompilationManager {
 
     /// Stop all running compilations except for the given solvers.
     pub async fn stop_all_except(&self, exception_solver_names: HashSet<String>) {
-        let solvers_to_stop = {
+        let exception_keys: HashSet<CacheKey> = exception_solver_names
+            .iter()
+            .map(|name| self.cache_key(name))
+            .collect();
+        let keys_to_stop = {
             self.compilations
                 .read()
                 .await
                 .iter()
-                .filter(|(name, _)| !exception_solver_names.contains(*name))
+                .filter(|(key, _)| !exception_keys.contains(*key))
                 .filter(|(_, compilation)| !matches!(compilation, Compilation::Done(_)))
-                .map(|(name, _)| name)
+                .map(|(key, _)| key)
                 .cloned()
                 .collect::<Vec<_>>()
         };
-        self.stop_many(solvers_to_stop.into_iter()).await;
+        self.stop_many_by_key(keys_to_stop.into_iter()).await;
     }
 }
 
+/// A cheap hash of a file based on its path and modification time
+fn file_hash(path: &Path) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    path.hash(&mut hasher);
+    if let Ok(modified) = std::fs::metadata(path).and_then(|metadata| metadata.modified()) {
+        modified.hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
 impl Drop for CompilationManager {
     fn drop(&mut self) {
         self.cancellation_token.cancel()
@@ -194,8 +276,8 @@ pub enum Error {
 
 #[derive(thiserror::Error, Debug, Clone)]
 pub enum WaitForError {
-    #[error("compilation was cancelled")]
-    Cancelled,
+    #[error("compilation cancelled for solver '{0}'")]
+    Cancelled(String),
     #[error(
         "a compilation for solver '{0}' was attempted to be retrieved, but one has not been started for that solver"
     )]
@@ -211,15 +293,37 @@ pub type WaitForResult = std::result::Result<Arc<Conversion>, WaitForError>;
 
 impl IsCancelled for WaitForError {
     fn is_cancelled(&self) -> bool {
-        matches!(self, Self::Cancelled)
+        matches!(self, Self::Cancelled(_))
     }
 }
 
 impl From<&compilation::Error> for WaitForError {
     fn from(value: &compilation::Error) -> Self {
         match value {
-            super::Error::Cancelled(_) => Self::Cancelled,
+            super::Error::Cancelled(solver_name) => Self::Cancelled(solver_name.clone()),
             super::Error::Conversion(_) => Self::Conversion,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cancelled_compilation_keeps_the_solver_name() {
+        let error = WaitForError::from(&compilation::Error::Cancelled("gecode".to_owned()));
+        assert!(matches!(&error, WaitForError::Cancelled(name) if name == "gecode"));
+        assert!(error.is_cancelled());
+    }
+
+    #[test]
+    fn test_failed_conversion_is_not_cancelled() {
+        let io_error = std::io::Error::from(std::io::ErrorKind::NotFound);
+        let error = WaitForError::from(&compilation::Error::Conversion(
+            compilation::ConversionError::Io(io_error),
+        ));
+        assert!(matches!(error, WaitForError::Conversion));
+        assert!(!error.is_cancelled());
+    }
+}
@@ -0,0 +1,119 @@
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use sha2::{Digest, Sha256};
+use tokio::process::Command;
+
+use crate::args::RunArgs;
+use crate::solver_config::Solver;
+use crate::{logging, storage};
+
+/// Cache entries older than this are removed at startup
+const MAX_AGE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+const FZN_FILE_NAME: &str = "model.fzn";
+const OZN_FILE_NAME: &str = "model.ozn";
+
+const CACHE_DIR: &str = "fzn";
+
+/// The directory of the cache entry for the compilation of the model and data for the solver.
+/// The hash covers the file contents (the data files in order), the solver, the output mode and the versions of
+/// MiniZinc and the solver, since they all affect the compilation.
+pub async fn entry_dir(
+    args: &RunArgs,
+    solver_name: &str,
+    solver: Option<&Solver>,
+) -> std::io::Result<Option<PathBuf>> {
+    let Some(root) = storage::cache_dir(CACHE_DIR) else {
+        return Ok(None);
+    };
+
+    let mut hasher = Sha256::new();
+    let mut update = |part: &[u8]| storage::update_part(&mut hasher, part);
+    update(&tokio::fs::read(&args.model).await?);
+    for data in args.data_files() {
+        update(&tokio::fs::read(data).await?);
+    }
+    update(solver_name.as_bytes());
+    update(args.output_mode.to_string().as_bytes());
+    update(&minizinc_version(&args.minizinc.minizinc_exe).await?);
+    update(
+        solver
+            .and_then(Solver::version)
+            .unwrap_or_default()
+            .as_bytes(),
+    );
+    update(
+        solver
+            .and_then(Solver::mznlib)
+            .unwrap_or_default()
+            .as_bytes(),
+    );
+    let hash = storage::sha256_hex(hasher);
+
+    Ok(Some(root.join(hash).join(solver_name)))
+}
+
+/// The output of `minizinc --version`
+async fn minizinc_version(minizinc_exe: &Path) -> std::io::Result<Vec<u8>> {
+    let output = Command::new(minizinc_exe).arg("--version").output().await?;
+    if !output.status.success() {
+        return Err(std::io::Error::other(format!(
+            "'{} --version' exited with {}",
+            minizinc_exe.display(),
+            output.status
+        )));
+    }
+    Ok(output.stdout)
+}
+
+/// Copies the cached compilation into the given files. Returns whether the entry existed.
+pub async fn load(entry_dir: &Path, fzn_path: &Path, ozn_path: &Path) -> std::io::Result<bool> {
+    let cached_fzn = entry_dir.join(FZN_FILE_NAME);
+    let cached_ozn = entry_dir.join(OZN_FILE_NAME);
+    if !tokio::fs::try_exists(&cached_fzn).await? || !tokio::fs::try_exists(&cached_ozn).await? {
+        return Ok(false);
+    }
+
+    tokio::fs::copy(cached_fzn, fzn_path).await?;
+    tokio::fs::copy(cached_ozn, ozn_path).await?;
+    Ok(true)
+}
+
+/// Stores the compilation in the cache
+pub async fn store(entry_dir: &Path, fzn_path: &Path, ozn_path: &Path) -> std::io::Result<()> {
+    tokio::fs::create_dir_all(entry_dir).await?;
+
+    for (source, file_name) in [(fzn_path, FZN_FILE_NAME), (ozn_path, OZN_FILE_NAME)] {
+        storage::copy_atomic(source, &entry_dir.join(file_name)).await?;
+    }
+    Ok(())
+}
+
+/// Removes the cache entries that are older than the maximum age
+pub fn prune_old_entries() {
+    let Some(root) = storage::cache_dir(CACHE_DIR) else {
+        return;
+    };
+    let Ok(entries) = std::fs::read_dir(&root) else {
+        // The cache has not been created yet
+        return;
+    };
+
+    let now = SystemTime::now();
+    for entry in entries.flatten() {
+        let is_old = entry
+            .metadata()
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| now.duration_since(modified).ok())
+            .is_some_and(|age| age > MAX_AGE);
+
+        if is_old && let Err(e) = std::fs::remove_dir_all(entry.path()) {
+            logging::warning!(
+                "failed to remove the old FlatZinc cache entry '{}': {e}",
+                entry.path().display()
+            );
+        }
+    }
+}
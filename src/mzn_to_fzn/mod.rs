@@ -1,5 +1,6 @@
 mod compilation;
 pub mod compilation_manager;
+pub mod disk_cache;
 
 pub use compilation::*;
 
@@ -21,4 +22,12 @@ impl Conversion {
     pub fn ozn(&self) -> &Path {
         self.ozn_file.path()
     }
+
+    pub async fn fzn_size_bytes(&self) -> std::io::Result<u64> {
+        Ok(tokio::fs::metadata(self.fzn_file.path()).await?.len())
+    }
+
+    pub async fn ozn_size_bytes(&self) -> std::io::Result<u64> {
+        Ok(tokio::fs::metadata(self.ozn_file.path()).await?.len())
+    }
 }
@@ -0,0 +1,95 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::{
+    logging,
+    scheduler::{Portfolio, SolverInfo},
+    solver_config,
+};
+
+/// A JSON portfolio definition, which is a richer alternative to the CSV schedule format.
+/// Example: `[{"id": "gecode", "cores": 2, "flags": ["-f"], "description": "...", "fallback": "chuffed"}]`
+#[derive(Debug, Deserialize)]
+#[serde(transparent)]
+pub struct PortfolioFile(Vec<PortfolioSolver>);
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PortfolioSolver {
+    id: String,
+    cores: usize,
+    /// Additional flags given to the solver
+    #[serde(default)]
+    flags: Vec<String>,
+    /// The solver version the portfolio was designed for
+    version: Option<String>,
+    description: Option<String>,
+    /// The solver to use instead if this solver has not been discovered
+    fallback: Option<String>,
+    /// Written to the stdin of the solver when it starts
+    hint_file: Option<PathBuf>,
+    /// In seconds, after which the solver is stopped and not started again
+    time_budget: Option<u64>,
+}
+
+impl PortfolioFile {
+    pub fn parse(s: &str) -> Result<Self> {
+        serde_json::from_str(s).map_err(Into::into)
+    }
+
+    /// Converts the portfolio definition into a portfolio, replacing the solvers that have not been
+    /// discovered with their fallbacks.
+    pub fn into_portfolio(self, solvers: &solver_config::Solvers) -> Portfolio {
+        self.0
+            .into_iter()
+            .map(|solver| solver.into_solver_info(solvers))
+            .collect()
+    }
+}
+
+impl PortfolioSolver {
+    fn into_solver_info(self, solvers: &solver_config::Solvers) -> SolverInfo {
+        if let Some(description) = &self.description {
+            logging::info!("Portfolio solver '{}': {description}", self.id);
+        }
+
+        let id = match (solvers.get_by_id(&self.id), self.fallback) {
+            (Some(solver), _) => {
+                if let Some(version) = &self.version
+                    && solver.version() != Some(version.as_str())
+                {
+                    logging::warning!(
+                        "The portfolio expects version '{version}' of solver '{}', but version '{}' was discovered",
+                        self.id,
+                        solver.version().unwrap_or("unknown")
+                    );
+                }
+                self.id
+            }
+            (None, Some(fallback)) => {
+                logging::warning!(
+                    "Solver '{}' from the portfolio was not found, using its fallback '{fallback}' instead",
+                    self.id
+                );
+                fallback
+            }
+            (None, None) => self.id,
+        };
+
+        let mut info = SolverInfo::new(id, self.cores);
+        info.flags = self.flags;
+        info.hint_file = self.hint_file;
+        info.time_budget = self.time_budget.map(Duration::from_secs);
+        info
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("the portfolio file is not valid JSON of the expected format")]
+    Json(#[from] serde_json::Error),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
@@ -1,16 +1,29 @@
+use crate::logging;
 use nix::sys::signal::{self, Signal};
 use nix::unistd;
 use std::collections::HashSet;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use sysinfo::{Pid, ProcessRefreshKind, RefreshKind, System};
+use tokio_util::sync::CancellationToken;
+
+/// How often the processes are checked for having exited during the grace period
+const EXIT_POLL_INTERVAL: Duration = Duration::from_millis(50);
 
 pub type Result<T> = std::result::Result<T, Error>;
 
 #[derive(Debug, thiserror::Error)]
 pub enum Error {}
 
+/// The single implementation for killing a solver and its children. It is done manually with `nix` and `sysinfo`
+/// instead of a crate like `kill-tree`, since we need control over the process group and the grace period.
 /// This function in intended to be called from a new thread from the actual program.
-pub fn recursive_force_kill(root_pid: u32) -> Result<()> {
+/// The processes are given the grace period to exit on their own, which ends early once they all have exited.
+/// The grace period is cut short if the cancellation token is cancelled, such that shutting down is not delayed.
+pub fn recursive_force_kill(
+    root_pid: u32,
+    grace_period: Duration,
+    cancellation_token: &CancellationToken,
+) -> Result<()> {
     let system = System::new_with_specifics(
         RefreshKind::nothing().with_processes(ProcessRefreshKind::nothing()),
     );
@@ -34,7 +47,18 @@ pub fn recursive_force_kill(root_pid: u32) -> Result<()> {
         collect_descendants(&system, target, &mut pids_to_kill);
     }
 
-    std::thread::sleep(Duration::from_secs(2));
+    let deadline = Instant::now() + grace_period;
+    while Instant::now() < deadline && !cancellation_token.is_cancelled() {
+        let root = Pid::from_u32(root_pid);
+        if pids_to_kill
+            .iter()
+            .chain([&root])
+            .all(|pid| !is_alive(*pid))
+        {
+            return Ok(());
+        }
+        std::thread::sleep(EXIT_POLL_INTERVAL);
+    }
 
     let system = System::new_with_specifics(
         RefreshKind::nothing().with_processes(ProcessRefreshKind::nothing()),
@@ -55,6 +79,11 @@ pub fn recursive_force_kill(root_pid: u32) -> Result<()> {
     Ok(())
 }
 
+/// Whether the process still exists, by sending it the null signal
+fn is_alive(pid: Pid) -> bool {
+    signal::kill(unistd::Pid::from_raw(pid.as_u32() as i32), None).is_ok()
+}
+
 pub fn send_signals_to_process_tree(pid: u32, signals: Vec<Signal>) -> Result<()> {
     let system = System::new_with_specifics(
         RefreshKind::nothing().with_processes(ProcessRefreshKind::nothing()),
@@ -92,13 +121,30 @@ pub fn send_signals_to_process_tree(pid: u32, signals: Vec<Signal>) -> Result<()
     Ok(())
 }
 
+/// Process trees deeper than this are not traversed further, to avoid overflowing the stack
+const MAX_DESCENDANT_DEPTH: usize = 500;
+
 pub fn collect_descendants(system: &System, parent: Pid, acc: &mut HashSet<Pid>) {
-    for (pid, process) in system.processes() {
-        if process.parent() == Some(parent) {
-            // If we haven't seen this child yet, add it and recurse
-            if acc.insert(*pid) {
-                collect_descendants(system, *pid, acc);
-            }
+    collect_descendants_with_depth(&|parent| children(system, parent), parent, acc, 0);
+}
+
+fn collect_descendants_with_depth(
+    children: &impl Fn(Pid) -> Vec<Pid>,
+    parent: Pid,
+    acc: &mut HashSet<Pid>,
+    depth: usize,
+) {
+    if depth > MAX_DESCENDANT_DEPTH {
+        logging::warning!(
+            "process tree of {parent} is deeper than {MAX_DESCENDANT_DEPTH} levels, not collecting further descendants"
+        );
+        return;
+    }
+
+    for pid in children(parent) {
+        // If we haven't seen this child yet, add it and recurse. This also prevents cycles.
+        if acc.insert(pid) {
+            collect_descendants_with_depth(children, pid, acc, depth + 1);
         }
     }
 }
@@ -112,20 +158,145 @@ pub fn get_process_pgid(pid: u32) -> Option<i32> {
 }
 
 pub fn get_process_tree_memory(system: &System, root_pid: u32) -> u64 {
-    let root_pid = Pid::from_u32(root_pid);
+    tree_memory(
+        Pid::from_u32(root_pid),
+        |pid| system.process(pid).map(|process| process.memory()),
+        |parent| children(system, parent),
+    )
+}
+
+fn children(system: &System, parent: Pid) -> Vec<Pid> {
+    system
+        .processes()
+        .iter()
+        .filter(|(_, process)| process.parent() == Some(parent))
+        .map(|(pid, _)| *pid)
+        .collect()
+}
+
+/// The memory of the root and its descendants, where `memory` is `None` for processes that do not exist
+fn tree_memory(
+    root_pid: Pid,
+    memory: impl Fn(Pid) -> Option<u64>,
+    children: impl Fn(Pid) -> Vec<Pid>,
+) -> u64 {
     let mut total_memory = 0u64;
     let mut pids_to_check = vec![root_pid];
+    // The snapshot can be stale, such that a process seems to have multiple parents or even be its own ancestor
+    let mut visited = HashSet::new();
 
     while let Some(pid) = pids_to_check.pop() {
-        if let Some(process) = system.process(pid) {
-            total_memory += process.memory();
-            for (child_pid, child_process) in system.processes() {
-                if child_process.parent() == Some(pid) {
-                    pids_to_check.push(*child_pid);
-                }
-            }
+        if !visited.insert(pid) {
+            continue;
+        }
+        if let Some(process_memory) = memory(pid) {
+            total_memory += process_memory;
+            pids_to_check.extend(children(pid));
         }
     }
 
     total_memory
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A snapshot of the processes as `(pid, parents, memory)`, where a stale snapshot can list multiple parents
+    struct Snapshot(Vec<(u32, Vec<u32>, u64)>);
+
+    impl Snapshot {
+        fn memory(&self, pid: Pid) -> Option<u64> {
+            self.0
+                .iter()
+                .find(|(process, _, _)| Pid::from_u32(*process) == pid)
+                .map(|(_, _, memory)| *memory)
+        }
+
+        fn children(&self, parent: Pid) -> Vec<Pid> {
+            self.0
+                .iter()
+                .filter(|(_, parents, _)| parents.iter().any(|p| Pid::from_u32(*p) == parent))
+                .map(|(pid, _, _)| Pid::from_u32(*pid))
+                .collect()
+        }
+
+        fn tree_memory(&self, root_pid: u32) -> u64 {
+            tree_memory(
+                Pid::from_u32(root_pid),
+                |pid| self.memory(pid),
+                |parent| self.children(parent),
+            )
+        }
+
+        fn descendants(&self, root_pid: u32) -> HashSet<Pid> {
+            let mut acc = HashSet::new();
+            collect_descendants_with_depth(
+                &|parent| self.children(parent),
+                Pid::from_u32(root_pid),
+                &mut acc,
+                0,
+            );
+            acc
+        }
+    }
+
+    fn pids(pids: &[u32]) -> HashSet<Pid> {
+        pids.iter().copied().map(Pid::from_u32).collect()
+    }
+
+    #[test]
+    fn test_descendants_terminate_on_cycles() {
+        let snapshot = Snapshot(vec![
+            (1, vec![3], 0),
+            (2, vec![1], 0),
+            (3, vec![2], 0),
+            (4, vec![4], 0),
+        ]);
+        assert_eq!(snapshot.descendants(1), pids(&[1, 2, 3]));
+        assert_eq!(snapshot.descendants(4), pids(&[4]));
+    }
+
+    #[test]
+    fn test_descendants_stop_at_the_depth_limit() {
+        let chain_length = 2 * MAX_DESCENDANT_DEPTH as u32;
+        let snapshot = Snapshot(
+            (1..=chain_length)
+                .map(|pid| (pid, vec![pid - 1], 0))
+                .collect(),
+        );
+        assert_eq!(snapshot.descendants(0).len(), MAX_DESCENDANT_DEPTH + 1);
+    }
+
+    #[test]
+    fn test_tree_memory_sums_the_descendants() {
+        let snapshot = Snapshot(vec![
+            (1, vec![], 1),
+            (2, vec![1], 10),
+            (3, vec![2], 100),
+            (4, vec![], 1000),
+        ]);
+        assert_eq!(snapshot.tree_memory(1), 111);
+        assert_eq!(snapshot.tree_memory(2), 110);
+        assert_eq!(snapshot.tree_memory(5), 0);
+    }
+
+    #[test]
+    fn test_tree_memory_counts_a_process_with_multiple_parents_once() {
+        // A diamond, where process 4 seems to be a child of both 2 and 3
+        let snapshot = Snapshot(vec![
+            (1, vec![], 1),
+            (2, vec![1], 10),
+            (3, vec![1], 100),
+            (4, vec![2, 3], 1000),
+        ]);
+        assert_eq!(snapshot.tree_memory(1), 1111);
+    }
+
+    #[test]
+    fn test_tree_memory_terminates_on_cycles() {
+        let snapshot = Snapshot(vec![(1, vec![2], 1), (2, vec![1], 10), (3, vec![3], 100)]);
+        assert_eq!(snapshot.tree_memory(1), 11);
+        assert_eq!(snapshot.tree_memory(3), 100);
+    }
+}
@@ -1,18 +1,26 @@
+mod solver_states;
+
 use crate::{
-    args::{RunArgs, Verbosity},
-    config::Config,
+    args::RunArgs,
+    cgroup::Cgroups,
+    config::{Config, RestartStrategy},
     logging,
-    model_parser::ObjectiveValue,
+    metrics_server::{self, Exposition},
+    model_parser::{ObjectiveType, ObjectiveValue},
     mzn_to_fzn::compilation_manager::CompilationManager,
     signal_handler::SignalEvent,
+    solution_writer::SolutionWriter,
     solver_config,
     solver_manager::{self, Error, SolverManager},
 };
+use serde::Serialize;
+use solver_states::SolverStates;
 use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use sysinfo::System;
-use tokio::sync::Mutex;
+use tokio::sync::{Mutex, Notify};
 use tokio_util::sync::CancellationToken;
 
 #[derive(Clone)]
@@ -24,7 +32,7 @@ impl SchedulerChildCancellationToken {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct ScheduleElement {
     pub id: u64,
     pub info: SolverInfo,
@@ -39,11 +47,21 @@ impl ScheduleElement {
 pub type Schedule = Vec<ScheduleElement>;
 pub type Portfolio = Vec<SolverInfo>;
 
-#[derive(Debug, Eq, PartialEq, Clone)]
+#[derive(Debug, Eq, PartialEq, Hash, Clone)]
 pub struct SolverInfo {
     pub name: String,
     pub cores: usize,
     pub objective: Option<ObjectiveValue>,
+    /// Additional flags given to the solver on top of the ones in the config
+    pub flags: Vec<String>,
+    /// If set, passed to the solver after the arguments from the config and the command line
+    pub extra_args: Option<Vec<String>>,
+    /// How long the solver may run before it is stopped for good
+    pub time_budget: Option<Duration>,
+    /// The AI session that scheduled the solver in the A/B test mode
+    pub session: Option<String>,
+    /// A file whose contents are written to the stdin of the solver when it starts, e.g. with hints
+    pub hint_file: Option<PathBuf>,
 }
 
 impl std::fmt::Display for SolverInfo {
@@ -66,6 +84,11 @@ impl SolverInfo {
             name,
             cores,
             objective: None,
+            flags: Vec::new(),
+            extra_args: None,
+            time_budget: None,
+            session: None,
+            hint_file: None,
         }
     }
 }
@@ -79,14 +102,16 @@ struct ScheduleChanges {
 
 #[derive(Debug)]
 struct State {
-    running_solvers: HashMap<u64, SolverInfo>,
-    suspended_solvers: HashMap<u64, SolverInfo>,
+    solvers: SolverStates,
     system: System,
     memory_limit: u64, // In bytes (0 = use system total)
     next_solver_id: u64,
     prev_objective: Option<ObjectiveValue>,
     config: Config,
-    debug_verbosity: Verbosity,
+    /// Whether the system-wide memory threshold is enforced
+    enforce_memory: bool,
+    /// Whether the total memory of 0 was reported, such that it is not repeated on every check
+    reported_zero_total_memory: bool,
 }
 
 pub struct Scheduler {
@@ -94,6 +119,7 @@ pub struct Scheduler {
     pub solver_manager: Arc<SolverManager>,
     scheduler_cancellation_token: CancellationToken,
     compilation_manager: Arc<CompilationManager>,
+    reload_requests: Arc<Notify>,
 }
 
 impl Drop for Scheduler {
@@ -102,31 +128,195 @@ impl Drop for Scheduler {
     }
 }
 
+/// How long the old solvers keep running with the overlap restart strategy, if none of the new ones produces output
+const OVERLAP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How often the running solvers are checked for stalls
+const STALL_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The status line that is printed on `SIGUSR1`
+#[derive(Serialize)]
+struct StatusLine {
+    best: Option<ObjectiveValue>,
+    solutions: u64,
+    uptime_secs: u64,
+    running_solvers: Vec<String>,
+}
+
+/// Prints the status of the solve as a JSON line to stderr, without affecting the solvers
+async fn print_status(state: &Mutex<State>, solver_manager: &SolverManager) {
+    let mut running_solvers: Vec<String> = state
+        .lock()
+        .await
+        .solvers
+        .running()
+        .values()
+        .map(|info| info.name.clone())
+        .collect();
+    running_solvers.sort();
+
+    let status = StatusLine {
+        best: solver_manager.get_best_objective(),
+        solutions: solver_manager.printed_solution_count(),
+        uptime_secs: solver_manager.uptime().as_secs(),
+        running_solvers,
+    };
+    match serde_json::to_string(&status) {
+        // Not a log message: the line was requested with `SIGUSR1`, so it is printed regardless of the verbosity,
+        // and without the log prefix, such that it stays a plain JSON line for the tools that parse it
+        Ok(line) => eprintln!("{line}"),
+        Err(e) => logging::error!(e.into()),
+    }
+}
+
+/// The live metrics that are served with `--metrics-port`
+async fn prometheus_metrics(state: &Mutex<State>, solver_manager: &SolverManager) -> String {
+    let (running_solvers, used_memory, total_memory) = {
+        let mut state = state.lock().await;
+        state.system.refresh_memory();
+        (
+            state.solvers.running().len(),
+            state.system.used_memory(),
+            state.system.total_memory(),
+        )
+    };
+    let mut solver_metrics: Vec<_> = solver_manager.metrics().await.into_iter().collect();
+    solver_metrics.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    let mut exposition = Exposition::default();
+    exposition.metric(
+        "parasol_solutions_total",
+        "counter",
+        "The number of solutions found per solver",
+        solver_metrics.iter().map(|(solver, metrics)| {
+            (
+                Some(("solver", solver.as_str())),
+                metrics.solution_count.to_string(),
+            )
+        }),
+    );
+    exposition.metric(
+        "parasol_best_objective",
+        "gauge",
+        "The best objective found by any solver",
+        solver_manager
+            .get_best_objective()
+            .map(|objective| (None, objective.to_string())),
+    );
+    exposition.metric(
+        "parasol_running_solvers",
+        "gauge",
+        "The number of solvers that are running and not suspended",
+        [(None, running_solvers.to_string())],
+    );
+    exposition.metric(
+        "parasol_memory_bytes",
+        "gauge",
+        "The used and the total memory of the system",
+        [
+            (Some(("type", "used")), used_memory.to_string()),
+            (Some(("type", "total")), total_memory.to_string()),
+        ],
+    );
+    exposition.metric(
+        "parasol_uptime_seconds",
+        "gauge",
+        "The seconds since the solver manager was started",
+        [(None, solver_manager.uptime().as_secs_f64().to_string())],
+    );
+    exposition.into_string()
+}
+
+/// The used and the total memory in bytes. A total of 0 is reported once in `reported_zero_total`.
+fn memory_usage(used: u64, total: u64, reported_zero_total: &mut bool) -> (f64, f64) {
+    if total == 0 {
+        // Treating the total as 1 byte and the usage as 0 means the threshold is never exceeded,
+        // which is safer than killing all solvers
+        if !*reported_zero_total {
+            *reported_zero_total = true;
+            logging::error_msg!(
+                "Total memory is reported as 0, so memory usage cannot be enforced"
+            );
+        }
+        return (0.0, 1.0);
+    }
+    (used as f64, total as f64)
+}
+
 fn is_over_threshold(used: f64, total: f64, threshold: f64) -> bool {
     used / total > threshold
 }
 
+/// The cgroups for the solvers if they are enabled with `--cgroups`. The solvers run without them if they cannot
+/// be set up, since the memory enforcer still enforces the limits.
+async fn cgroups(args: &RunArgs, config: &Config) -> Option<Cgroups> {
+    if !args.cgroups {
+        return None;
+    }
+    if config.memory_per_solver_bytes.is_none() && config.solver_memory_limits_bytes.is_empty() {
+        logging::warning!("--cgroups has no effect without a memory limit per solver");
+        return None;
+    }
+
+    match Cgroups::new(
+        config.memory_per_solver_bytes,
+        config.solver_memory_limits_bytes.clone(),
+    )
+    .await
+    {
+        Ok(cgroups) => Some(cgroups),
+        Err(e) => {
+            logging::error!(e.into());
+            logging::warning!("continuing without cgroups");
+            None
+        }
+    }
+}
+
 impl Scheduler {
     pub async fn new(
         args: &RunArgs,
         config: &Config,
         solver_info: Arc<solver_config::Solvers>,
         compilation_manager: Arc<CompilationManager>,
+        solution_writer: SolutionWriter,
         program_cancellation_token: CancellationToken,
         mut suspend_and_resume_signal_rx: tokio::sync::mpsc::UnboundedReceiver<SignalEvent>,
     ) -> std::result::Result<Self, Error> {
         let scheduler_cancellation_token = program_cancellation_token.child_token();
-        let solver_manager = Arc::new(
-            SolverManager::new(
-                args.clone(),
-                config.solver_args.clone(),
-                solver_info,
-                compilation_manager.clone(),
-                program_cancellation_token.clone(),
-            )
-            .await?,
-        );
+        let solver_manager = SolverManager::new(
+            args.clone(),
+            config.solver_args.clone(),
+            config.max_solver_restarts,
+            Duration::from_secs(config.solver_kill_grace_secs),
+            cgroups(args, config).await,
+            solver_info,
+            compilation_manager.clone(),
+            solution_writer,
+            program_cancellation_token.clone(),
+        )
+        .await?;
+
+        let memory_limit = std::env::var("MEMORY_LIMIT")
+            .ok()
+            .and_then(|val| val.parse::<u64>().ok())
+            .map(|mib| mib * 1024 * 1024)
+            .unwrap_or(0);
+
+        let state = Arc::new(Mutex::new(State {
+            solvers: SolverStates::default(),
+            system: System::new_all(),
+            memory_limit,
+            next_solver_id: 0,
+            prev_objective: None,
+            config: config.clone(),
+            enforce_memory: args.enforce_memory,
+            reported_zero_total_memory: false,
+        }));
 
+        let reload_requests = Arc::new(Notify::new());
+        let reload_requests_clone = reload_requests.clone();
+        let state_clone = state.clone();
         let solver_manager_clone = solver_manager.clone();
         let scheduler_cancellation_token_clone = scheduler_cancellation_token.clone();
         tokio::spawn(async move {
@@ -142,6 +332,14 @@ impl Scheduler {
                                 res
                             }
                             SignalEvent::Resume => solver_manager_clone.resume_all_solvers().await,
+                            SignalEvent::Status => {
+                                print_status(&state_clone, &solver_manager_clone).await;
+                                Ok(())
+                            }
+                            SignalEvent::ReloadSchedule => {
+                                reload_requests_clone.notify_one();
+                                Ok(())
+                            }
                         };
                         if let Err(e) = result {
                             handle_schedule_errors(e);
@@ -151,34 +349,53 @@ impl Scheduler {
             }
         });
 
-        let memory_limit = std::env::var("MEMORY_LIMIT")
-            .ok()
-            .and_then(|val| val.parse::<u64>().ok())
-            .map(|mib| mib * 1024 * 1024)
-            .unwrap_or(0);
-
-        let debug_verbosity = args.verbosity;
-
-        let state = Arc::new(Mutex::new(State {
-            running_solvers: HashMap::new(),
-            suspended_solvers: HashMap::new(),
-            system: System::new_all(),
-            memory_limit,
-            next_solver_id: 0,
-            prev_objective: None,
-            config: config.clone(),
-            debug_verbosity,
-        }));
+        if let Some(port) = args.metrics_port {
+            let state_clone = state.clone();
+            let solver_manager_clone = solver_manager.clone();
+            let render = move || {
+                let state = state_clone.clone();
+                let solver_manager = solver_manager_clone.clone();
+                async move { prometheus_metrics(&state, &solver_manager).await }
+            };
+            let scheduler_cancellation_token_clone = scheduler_cancellation_token.clone();
+            tokio::spawn(async move {
+                if let Err(e) =
+                    metrics_server::serve(port, render, scheduler_cancellation_token_clone).await
+                {
+                    logging::error_msg!("failed to serve the metrics on port {port}: {e}");
+                }
+            });
+        }
 
         let state_clone = state.clone();
         let solver_manager_clone = solver_manager.clone();
         let config_clone = config.clone();
-        if args.enforce_memory {
+        if args.enforce_memory
+            || config.memory_per_solver_bytes.is_some()
+            || !config.solver_memory_limits_bytes.is_empty()
+        {
             let scheduler_cancellation_token_clone = scheduler_cancellation_token.clone();
+            let enforce_memory = args.enforce_memory;
             tokio::spawn(async move {
                 tokio::select! {
                     _ = scheduler_cancellation_token_clone.cancelled() => {},
-                    _ = Self::memory_enforcer_loop(state_clone, solver_manager_clone, config_clone) => {}
+                    _ = Self::memory_enforcer_loop(state_clone, solver_manager_clone, config_clone, enforce_memory) => {}
+                }
+            });
+        }
+
+        // Satisfaction problems have no objective to improve, so every solver would look stalled
+        if let Some(stall_timeout) = config.stall_timeout_secs
+            && solver_manager.objective_type() != ObjectiveType::Satisfy
+        {
+            let state_clone = state.clone();
+            let solver_manager_clone = solver_manager.clone();
+            let scheduler_cancellation_token_clone = scheduler_cancellation_token.clone();
+            let stall_timeout = Duration::from_secs(stall_timeout);
+            tokio::spawn(async move {
+                tokio::select! {
+                    _ = scheduler_cancellation_token_clone.cancelled() => {},
+                    _ = Self::stall_detector_loop(state_clone, solver_manager_clone, stall_timeout) => {}
                 }
             });
         }
@@ -188,9 +405,15 @@ impl Scheduler {
             solver_manager,
             scheduler_cancellation_token,
             compilation_manager,
+            reload_requests,
         })
     }
 
+    /// Waits until the schedule is requested to be reloaded with `SIGUSR2`
+    pub async fn reload_requested(&self) {
+        self.reload_requests.notified().await
+    }
+
     pub fn create_apply_token(&self) -> SchedulerChildCancellationToken {
         SchedulerChildCancellationToken(self.scheduler_cancellation_token.child_token())
     }
@@ -201,13 +424,16 @@ impl Scheduler {
             .refresh_processes(sysinfo::ProcessesToUpdate::All, false);
         state.system.refresh_memory();
 
-        let used = state.system.used_memory() as f64;
         let total = if state.memory_limit > 0 {
-            state.memory_limit as f64
+            state.memory_limit
         } else {
-            state.system.total_memory() as f64
+            state.system.total_memory()
         };
-        (used, total)
+        memory_usage(
+            state.system.used_memory(),
+            total,
+            &mut state.reported_zero_total_memory,
+        )
     }
 
     async fn kill_suspended_until_under_threshold(
@@ -216,16 +442,22 @@ impl Scheduler {
         mut used_memory: f64,
         total_memory: f64,
     ) -> f64 {
-        let ids: Vec<u64> = state.suspended_solvers.keys().copied().collect();
+        let ids: Vec<u64> = state.solvers.suspended().keys().copied().collect();
         let mut sorted = solver_manager
             .solvers_sorted_by_mem(&ids, &state.system)
             .await;
 
+        // The suspended solvers are killed until the memory is under the resume threshold, such that the
+        // remaining ones can be resumed without going over the threshold right away
         while !sorted.is_empty()
-            && is_over_threshold(used_memory, total_memory, state.config.memory_threshold)
+            && is_over_threshold(
+                used_memory,
+                total_memory,
+                state.config.memory_resume_threshold,
+            )
         {
             let (mem, id) = sorted.remove(0);
-            state.suspended_solvers.remove(&id);
+            state.solvers.stop(id);
             if let Err(e) = solver_manager.stop_solver(id).await {
                 logging::error!(e.into());
             } else {
@@ -241,9 +473,15 @@ impl Scheduler {
         mut used_memory: f64,
         total_memory: f64,
     ) -> f64 {
-        let ids: Vec<u64> = state.running_solvers.keys().copied().collect();
-        let total_cores: usize = state.running_solvers.values().map(|info| info.cores).sum();
+        let ids: Vec<u64> = state.solvers.running().keys().copied().collect();
+        let total_cores: usize = state
+            .solvers
+            .running()
+            .values()
+            .map(|info| info.cores)
+            .sum();
         if total_cores == 0 {
+            // No running solver uses any cores, so there is no fair share to compare against
             return used_memory;
         }
 
@@ -256,7 +494,7 @@ impl Scheduler {
         let mut remaining = Vec::new();
 
         for (solver_mem, id) in sorted {
-            let cores = match state.running_solvers.get(&id) {
+            let cores = match state.solvers.running().get(&id) {
                 Some(info) => info.cores as u64,
                 None => {
                     // should never fail since the state is locked however error logging just for safety
@@ -268,7 +506,7 @@ impl Scheduler {
             };
             if solver_mem / cores > per_core_threshold {
                 // use number of cores a process has to decide if it uses more that its fair share
-                state.running_solvers.remove(&id);
+                state.solvers.stop(id);
                 if let Err(e) = solver_manager.stop_solver(id).await {
                     logging::error_msg!("failed to stop running solver: {e}");
                 } else {
@@ -282,7 +520,7 @@ impl Scheduler {
             && is_over_threshold(used_memory, total_memory, state.config.memory_threshold)
         {
             let (mem, id) = remaining.remove(0);
-            state.running_solvers.remove(&id);
+            state.solvers.stop(id);
             if let Err(e) = solver_manager.stop_solver(id).await {
                 logging::error_msg!("failed to stop running solver: {e}");
             } else {
@@ -292,17 +530,89 @@ impl Scheduler {
         used_memory
     }
 
+    /// Suspends the running solvers that use more memory than the limit. They stay suspended until the next
+    /// schedule resumes them.
+    async fn suspend_running_over_limit(
+        state: &mut State,
+        solver_manager: &Arc<SolverManager>,
+        limit_bytes: u64,
+    ) {
+        let ids: Vec<u64> = state.solvers.running().keys().copied().collect();
+        let sorted = solver_manager
+            .solvers_sorted_by_mem(&ids, &state.system)
+            .await;
+
+        for (mem, id) in sorted.into_iter().filter(|(mem, _)| *mem > limit_bytes) {
+            logging::info!(
+                "solver {id} uses {} MiB, which is over the memory limit per solver, suspending it",
+                mem / (1024 * 1024)
+            );
+            if let Err(e) = solver_manager.suspend_solver(id).await {
+                logging::error!(e.into());
+                continue;
+            }
+            state.solvers.suspend(id);
+        }
+    }
+
+    /// Stops the solvers that use more memory than the limit for their solver, whether they are running or
+    /// suspended.
+    async fn stop_over_solver_limits(
+        state: &mut State,
+        solver_manager: &Arc<SolverManager>,
+        limits_bytes: &HashMap<String, u64>,
+    ) {
+        let ids: Vec<u64> = state
+            .solvers
+            .running()
+            .iter()
+            .chain(state.solvers.suspended())
+            .filter(|(_, info)| limits_bytes.contains_key(&info.name))
+            .map(|(id, _)| *id)
+            .collect();
+        if ids.is_empty() {
+            return;
+        }
+        let solver_mem = solver_manager
+            .solvers_sorted_by_mem(&ids, &state.system)
+            .await;
+
+        for (mem, id) in solver_mem {
+            let Some(info) = state.solvers.get(id) else {
+                continue;
+            };
+            let Some(&limit_bytes) = limits_bytes.get(&info.name) else {
+                continue;
+            };
+            if mem <= limit_bytes {
+                continue;
+            }
+
+            logging::info!(
+                "solver '{}' ({id}) uses {} MiB, which is over its limit of {} MiB, stopping it",
+                info.name,
+                mem / (1024 * 1024),
+                limit_bytes / (1024 * 1024)
+            );
+            if let Err(e) = solver_manager.stop_solver(id).await {
+                logging::error!(e.into());
+                continue;
+            }
+            state.solvers.stop(id);
+        }
+    }
+
     async fn remove_exited_solvers(state: &mut State, solver_manager: &Arc<SolverManager>) {
         let active = solver_manager.active_solver_ids().await;
 
-        state.running_solvers.retain(|id, _| active.contains(id));
-        state.suspended_solvers.retain(|id, _| active.contains(id));
+        state.solvers.retain(|id| active.contains(&id));
     }
 
     async fn memory_enforcer_loop(
         state: Arc<Mutex<State>>,
         solver_manager: Arc<SolverManager>,
         config: Config,
+        enforce_memory: bool,
     ) {
         let mut interval =
             tokio::time::interval(Duration::from_secs(config.memory_enforcer_interval));
@@ -312,7 +622,21 @@ impl Scheduler {
             let mut state: tokio::sync::MutexGuard<'_, State> = state.lock().await;
             Self::remove_exited_solvers(&mut state, &solver_manager).await;
             let (used, total) = Self::get_memory_usage(&mut state);
-            if !is_over_threshold(used, total, config.memory_threshold) {
+
+            if !config.solver_memory_limits_bytes.is_empty() {
+                Self::stop_over_solver_limits(
+                    &mut state,
+                    &solver_manager,
+                    &config.solver_memory_limits_bytes,
+                )
+                .await;
+            }
+
+            if let Some(limit_bytes) = config.memory_per_solver_bytes {
+                Self::suspend_running_over_limit(&mut state, &solver_manager, limit_bytes).await;
+            }
+
+            if !enforce_memory || !is_over_threshold(used, total, config.memory_threshold) {
                 continue;
             }
 
@@ -339,6 +663,90 @@ impl Scheduler {
         }
     }
 
+    async fn stall_detector_loop(
+        state: Arc<Mutex<State>>,
+        solver_manager: Arc<SolverManager>,
+        stall_timeout: Duration,
+    ) {
+        let mut interval = tokio::time::interval(STALL_CHECK_INTERVAL);
+
+        loop {
+            interval.tick().await;
+            let mut state = state.lock().await;
+            Self::remove_exited_solvers(&mut state, &solver_manager).await;
+            Self::rotate_stalled_solvers(&mut state, &solver_manager, stall_timeout).await;
+        }
+    }
+
+    /// Swaps every stalled running solver for a suspended one that fits in its cores. The suspended solver that
+    /// improved least recently is resumed first, such that the solvers take turns.
+    /// A stalled solver keeps running if there is no suspended solver to replace it, since the cores would
+    /// otherwise be idle.
+    async fn rotate_stalled_solvers(
+        state: &mut State,
+        solver_manager: &Arc<SolverManager>,
+        stall_timeout: Duration,
+    ) {
+        let running: Vec<u64> = state.solvers.running().keys().copied().collect();
+        let suspended: Vec<u64> = state.solvers.suspended().keys().copied().collect();
+        if suspended.is_empty() {
+            return;
+        }
+
+        let now = Instant::now();
+        let stalled: Vec<u64> = solver_manager
+            .last_improvements(&running)
+            .await
+            .into_iter()
+            .filter(|(_, last_improved_at)| now.duration_since(*last_improved_at) > stall_timeout)
+            .map(|(id, _)| id)
+            .collect();
+        if stalled.is_empty() {
+            return;
+        }
+
+        let mut candidates: Vec<(u64, Instant)> = solver_manager
+            .last_improvements(&suspended)
+            .await
+            .into_iter()
+            .collect();
+        candidates.sort_by_key(|(_, last_improved_at)| *last_improved_at);
+
+        for id in stalled {
+            let Some(cores) = state.solvers.running().get(&id).map(|info| info.cores) else {
+                continue;
+            };
+            let Some(position) = candidates.iter().position(|(candidate, _)| {
+                state
+                    .solvers
+                    .suspended()
+                    .get(candidate)
+                    .is_some_and(|info| info.cores <= cores)
+            }) else {
+                continue;
+            };
+            let (replacement, _) = candidates.remove(position);
+
+            logging::info!(
+                "solver {id} has not improved for {stall_timeout:?}, suspending it and resuming solver {replacement}"
+            );
+            if let Err(e) = solver_manager.suspend_solver(id).await {
+                logging::error!(e.into());
+                continue;
+            }
+            if let Err(e) = solver_manager.resume_solver(replacement).await {
+                logging::error!(e.into());
+                if let Err(e) = solver_manager.resume_solver(id).await {
+                    logging::error!(e.into());
+                }
+                continue;
+            }
+
+            state.solvers.suspend(id);
+            state.solvers.resume(replacement);
+        }
+    }
+
     async fn categorize_schedule(
         schedule: Schedule,
         state: &mut State,
@@ -349,8 +757,8 @@ impl Scheduler {
         let mut to_start = Vec::new();
         let mut to_resume = Vec::new();
         let mut keep_running = Vec::new();
-        let mut running: HashSet<_> = state.running_solvers.keys().copied().collect();
-        let mut suspended: HashSet<_> = state.suspended_solvers.keys().copied().collect();
+        let mut running: HashSet<_> = state.solvers.running().keys().copied().collect();
+        let mut suspended: HashSet<_> = state.solvers.suspended().keys().copied().collect();
 
         for elem in schedule {
             if running.remove(&elem.id) {
@@ -372,40 +780,49 @@ impl Scheduler {
         }
     }
 
+    /// Suspended solvers are only resumed once the memory usage is under the resume threshold, such that they are
+    /// not suspended again right away by the memory enforcer.
+    fn keep_suspended_while_over_resume_threshold(
+        state: &mut State,
+        changes: &mut ScheduleChanges,
+    ) {
+        if !state.enforce_memory || changes.to_resume.is_empty() {
+            return;
+        }
+
+        let (used, total) = Self::get_memory_usage(state);
+        if is_over_threshold(used, total, state.config.memory_resume_threshold) {
+            logging::info!(
+                "memory usage is over the resume threshold, keeping solvers {:?} suspended",
+                changes.to_resume
+            );
+            changes.to_resume.clear();
+        }
+    }
+
     fn apply_changes_to_state(state: &mut State, changes: &ScheduleChanges) {
         for elem in &changes.to_start {
-            state.running_solvers.insert(elem.id, elem.info.clone());
+            state.solvers.start(elem.id, elem.info.clone());
         }
 
         for &id in &changes.to_resume {
-            if let Some(info) = state.suspended_solvers.remove(&id) {
-                state.running_solvers.insert(id, info);
-            }
+            state.solvers.resume(id);
         }
 
         for &id in &changes.to_suspend {
-            if let Some(info) = state.running_solvers.remove(&id) {
-                state.suspended_solvers.insert(id, info);
-            }
+            state.solvers.suspend(id);
         }
     }
 
-    pub async fn apply(
-        &mut self,
-        portfolio: Portfolio,
-        apply_cancellation_token: SchedulerChildCancellationToken,
-        stop_other_compiling_solvers: bool,
-    ) -> std::result::Result<(), Vec<Error>> {
-        if stop_other_compiling_solvers {
-            let solver_to_keep_compiling =
-                portfolio.iter().map(|info| info.name.to_string()).collect();
-            self.compilation_manager
-                .stop_all_except(solver_to_keep_compiling)
-                .await;
-        }
-
-        let mut state = self.state.lock().await;
-        let new_objective = self.solver_manager.get_best_objective().await;
+    /// Stops the solvers whose best objective is worse than the overall best objective, such that they can be
+    /// restarted with the new bound.
+    /// With the overlap restart strategy, the solvers are only removed from the state and keep running. Their ids
+    /// are returned, such that they can be stopped once the restarted solvers are running.
+    async fn restart_solvers_on_new_objective(
+        &self,
+        state: &mut State,
+    ) -> std::result::Result<Vec<u64>, Vec<Error>> {
+        let new_objective = self.solver_manager.get_best_objective();
 
         if new_objective != state.prev_objective {
             logging::info!(
@@ -432,29 +849,124 @@ impl Scheduler {
                     logging::info!("solver to restart {:?}", to_restart);
                 }
 
-                self.solver_manager.stop_solvers(&to_restart).await?;
+                // Suspended solvers are not working anyway, so they are always stopped right away
+                let (to_overlap, to_stop): (Vec<u64>, Vec<u64>) =
+                    to_restart.into_iter().partition(|id| {
+                        state.config.restart_strategy == RestartStrategy::Overlap
+                            && state.solvers.running().contains_key(id)
+                    });
 
-                for id in &to_restart {
-                    state.running_solvers.remove(id);
-                    state.suspended_solvers.remove(id);
+                self.solver_manager.stop_solvers(&to_stop).await?;
+
+                for id in to_stop.iter().chain(&to_overlap) {
+                    state.solvers.stop(*id);
                 }
+
+                return Ok(to_overlap);
             }
         }
 
-        let schedule = Self::assign_ids(portfolio, &mut state);
-        let changes =
-            Self::categorize_schedule(schedule.clone(), &mut state, self.solver_manager.clone())
+        Ok(Vec::new())
+    }
+
+    /// Stops the old solvers once one of the new solvers has produced output, or after a timeout
+    fn stop_after_overlap(&self, old_solvers: Vec<u64>, new_solvers: Vec<u64>) {
+        let solver_manager = self.solver_manager.clone();
+        let cancellation_token = self.scheduler_cancellation_token.clone();
+        tokio::spawn(async move {
+            // Without new solvers there is nothing to wait for
+            if !new_solvers.is_empty() {
+                tokio::select! {
+                    _ = solver_manager.wait_for_first_output(&new_solvers) => {}
+                    _ = tokio::time::sleep(OVERLAP_TIMEOUT) => {
+                        logging::info!("none of the restarted solvers produced output in time, stopping the old ones anyway");
+                    }
+                    // The solvers are all stopped on cancellation
+                    _ = cancellation_token.cancelled() => return,
+                }
+            }
+            if let Err(errors) = solver_manager.stop_solvers(&old_solvers).await {
+                handle_schedule_errors(errors);
+            }
+        });
+    }
+
+    pub async fn apply(
+        &mut self,
+        portfolio: Portfolio,
+        apply_cancellation_token: SchedulerChildCancellationToken,
+        stop_other_compiling_solvers: bool,
+    ) -> std::result::Result<(), Vec<Error>> {
+        if stop_other_compiling_solvers {
+            let solver_to_keep_compiling =
+                portfolio.iter().map(|info| info.name.to_string()).collect();
+            self.compilation_manager
+                .stop_all_except(solver_to_keep_compiling)
                 .await;
-        Self::apply_changes_to_state(&mut state, &changes);
+        }
+
+        let (schedule, changes, overlapping_solvers) = {
+            let mut state = self.state.lock().await;
+            // Satisfaction problems have no objective, so there is never a better bound to restart solvers with.
+            // The objective comparison would also skip it (both objectives are always None), but only by coincidence.
+            let overlapping_solvers =
+                if self.solver_manager.objective_type() != ObjectiveType::Satisfy {
+                    self.restart_solvers_on_new_objective(&mut state).await?
+                } else {
+                    Vec::new()
+                };
+
+            let State {
+                solvers,
+                next_solver_id,
+                ..
+            } = &mut *state;
+            let schedule = Self::assign_ids(portfolio, solvers, next_solver_id);
+            let mut changes = Self::categorize_schedule(
+                schedule.clone(),
+                &mut state,
+                self.solver_manager.clone(),
+            )
+            .await;
+            Self::keep_suspended_while_over_resume_threshold(&mut state, &mut changes);
+            (schedule, changes, overlapping_solvers)
+        };
 
-        if state.debug_verbosity >= Verbosity::Info
-            && (!changes.to_start.is_empty()
-                || !changes.to_suspend.is_empty()
-                || !changes.to_resume.is_empty())
+        if !changes.to_start.is_empty()
+            || !changes.to_suspend.is_empty()
+            || !changes.to_resume.is_empty()
         {
             logging::info!("changes: {:?}", changes);
         }
 
+        // The state is not locked while the solvers are changed, such that the memory enforcer is not blocked.
+        // This means the memory enforcer does not know about the changes until they are applied to the state below,
+        // e.g. it will not kill the newly started solvers yet. This is acceptable, since it runs again shortly after.
+        // If it kills a solver that is being resumed or suspended, the solver is no longer in the state and
+        // `apply_changes_to_state` skips it.
+        let result = self
+            .change_solvers(&schedule, &changes, apply_cancellation_token)
+            .await;
+
+        if !overlapping_solvers.is_empty() {
+            let new_solvers = changes.to_start.iter().map(|elem| elem.id).collect();
+            self.stop_after_overlap(overlapping_solvers, new_solvers);
+        }
+
+        let mut state = self.state.lock().await;
+        Self::apply_changes_to_state(&mut state, &changes);
+        // The solvers that were skipped, e.g. because their compilation failed, do not hold on to their cores
+        Self::remove_exited_solvers(&mut state, &self.solver_manager).await;
+
+        result
+    }
+
+    async fn change_solvers(
+        &self,
+        schedule: &Schedule,
+        changes: &ScheduleChanges,
+        apply_cancellation_token: SchedulerChildCancellationToken,
+    ) -> std::result::Result<(), Vec<Error>> {
         if let Err(e) = self
             .solver_manager
             .suspend_solvers(&changes.to_suspend)
@@ -466,72 +978,155 @@ impl Scheduler {
                 .await?;
         }
 
+        let mut errors = Vec::new();
         if let Err(e) = self.solver_manager.resume_solvers(&changes.to_resume).await {
             logging::error_msg!("Failed to resume solvers: {e:?}");
             let mut resume_elements = Vec::new();
-            for schedule_elem in &schedule {
+            for schedule_elem in schedule {
                 for resume_id in &changes.to_resume {
                     if &schedule_elem.id == resume_id {
                         resume_elements.push(schedule_elem.clone());
                     }
                 }
             }
-            self.solver_manager
+            if let Err(e) = self
+                .solver_manager
                 .start_solvers(&resume_elements, apply_cancellation_token.0.clone())
-                .await;
+                .await
+            {
+                errors.extend(e);
+            }
         }
 
-        self.solver_manager
+        if let Err(e) = self
+            .solver_manager
             .start_solvers(&changes.to_start, apply_cancellation_token.0)
-            .await;
+            .await
+        {
+            errors.extend(e);
+        }
 
-        Ok(())
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
     }
 
+    /// Reuses the ids of the running and suspended solvers whose info matches an element of the portfolio,
+    /// preferring running solvers, and assigns new ids to the rest.
     fn assign_ids(
         portfolio: Portfolio,
-        state: &mut tokio::sync::MutexGuard<'_, State>,
+        solvers: &SolverStates,
+        next_solver_id: &mut u64,
     ) -> Schedule {
-        let running_solvers: Vec<(u64, SolverInfo)> = state
-            .running_solvers
-            .iter()
-            .map(|(k, v)| (*k, v.clone()))
-            .collect();
-
-        let suspended_solvers: Vec<(u64, SolverInfo)> = state
-            .suspended_solvers
+        // How many of the ids of each info are already reused, such that each id is only reused once
+        let mut reused_counts: HashMap<&SolverInfo, usize> = HashMap::new();
+        let reused_ids: Vec<Option<u64>> = portfolio
             .iter()
-            .map(|(k, v)| (*k, v.clone()))
+            .map(|new_info| {
+                let reused_count = reused_counts.entry(new_info).or_default();
+                let id = solvers.ids(new_info)?.get(*reused_count).copied();
+                *reused_count += 1;
+                id
+            })
             .collect();
 
-        let mut solvers = running_solvers;
-        solvers.extend(suspended_solvers);
+        portfolio
+            .into_iter()
+            .zip(reused_ids)
+            .map(|(new_info, id)| {
+                let id = id.unwrap_or_else(|| {
+                    let id = *next_solver_id;
+                    *next_solver_id += 1;
+                    id
+                });
+                ScheduleElement::new(id, new_info)
+            })
+            .collect()
+    }
+}
 
-        let mut schedule = Vec::new();
-        for new_info in portfolio.into_iter() {
-            let mut i = 0;
+fn handle_schedule_errors(errors: Vec<solver_manager::Error>) {
+    logging::error_msg!("got the following errors when applying the schedule:");
+    errors.into_iter().for_each(|e| logging::error!(e.into()));
+}
 
-            while i < solvers.len() && new_info != solvers[i].1 {
-                i += 1
-            }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_equal_solver_infos_hash_the_same() {
+        let mut with_objective = SolverInfo::new("gecode".to_owned(), 2);
+        with_objective.objective = Some(ObjectiveValue::Float(0.0));
+        let mut with_negative_zero = with_objective.clone();
+        with_negative_zero.objective = Some(ObjectiveValue::Float(-0.0));
+
+        let infos: HashSet<SolverInfo> = [
+            SolverInfo::new("gecode".to_owned(), 2),
+            SolverInfo::new("gecode".to_owned(), 2),
+            SolverInfo::new("gecode".to_owned(), 1),
+            SolverInfo::new("chuffed".to_owned(), 2),
+            with_objective.clone(),
+            with_negative_zero,
+        ]
+        .into_iter()
+        .collect();
+
+        assert_eq!(infos.len(), 4);
+        assert!(infos.contains(&SolverInfo::new("gecode".to_owned(), 2)));
+        assert!(infos.contains(&with_objective));
+    }
 
-            let id = if i < solvers.len() {
-                solvers.remove(i).0
-            } else {
-                let id = state.next_solver_id;
-                state.next_solver_id += 1;
-                id
-            };
+    #[test]
+    fn test_zero_total_memory_is_never_over_the_threshold() {
+        let mut reported_zero_total = false;
+        let (used, total) = memory_usage(8 * 1024 * 1024, 0, &mut reported_zero_total);
+        assert!(reported_zero_total);
+        assert!(!is_over_threshold(used, total, 0.9));
+        assert!(!is_over_threshold(used, total, 0.0));
+
+        let (used, total) = memory_usage(9, 10, &mut reported_zero_total);
+        assert!(!is_over_threshold(used, total, 0.9));
+        assert!(is_over_threshold(used, total, 0.8));
+    }
 
-            let elem = ScheduleElement::new(id, new_info);
-            schedule.push(elem);
-        }
+    fn ids(schedule: &Schedule) -> Vec<u64> {
+        schedule.iter().map(|elem| elem.id).collect()
+    }
 
-        schedule
+    #[test]
+    fn test_assign_ids_reuses_running_solvers_first() {
+        let gecode = SolverInfo::new("gecode".to_owned(), 1);
+        let chuffed = SolverInfo::new("chuffed".to_owned(), 1);
+        let mut solvers = SolverStates::default();
+        solvers.start(0, gecode.clone());
+        solvers.start(1, gecode.clone());
+        solvers.suspend(0);
+        let mut next_solver_id = 2;
+
+        let schedule = Scheduler::assign_ids(vec![gecode.clone()], &solvers, &mut next_solver_id);
+        assert_eq!(ids(&schedule), [1]);
+
+        let portfolio = vec![gecode.clone(), chuffed, gecode.clone(), gecode];
+        let schedule = Scheduler::assign_ids(portfolio, &solvers, &mut next_solver_id);
+        assert_eq!(ids(&schedule), [1, 2, 0, 3]);
+        assert_eq!(next_solver_id, 4);
     }
-}
 
-fn handle_schedule_errors(errors: Vec<solver_manager::Error>) {
-    logging::error_msg!("got the following errors when applying the schedule:");
-    errors.into_iter().for_each(|e| logging::error!(e.into()));
+    #[test]
+    fn test_schedule_elements_hash_by_id_and_info() {
+        let info = SolverInfo::new("gecode".to_owned(), 2);
+        let elements: HashSet<ScheduleElement> = [
+            ScheduleElement::new(1, info.clone()),
+            ScheduleElement::new(1, info.clone()),
+            ScheduleElement::new(2, info.clone()),
+        ]
+        .into_iter()
+        .collect();
+
+        assert_eq!(elements.len(), 2);
+        assert!(elements.contains(&ScheduleElement::new(2, info)));
+    }
 }
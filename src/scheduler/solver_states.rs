@@ -0,0 +1,191 @@
+use super::SolverInfo;
+use std::collections::{HashMap, VecDeque};
+
+/// The running and suspended solvers, with an index from their info to their ids.
+/// The solvers are only changed through the transitions, such that the index is always in sync.
+#[derive(Debug, Default)]
+pub struct SolverStates {
+    running: HashMap<u64, SolverInfo>,
+    suspended: HashMap<u64, SolverInfo>,
+    /// The ids of the solvers with the info, where the running solvers come before the suspended ones
+    ids_by_info: HashMap<SolverInfo, VecDeque<u64>>,
+}
+
+impl SolverStates {
+    pub fn running(&self) -> &HashMap<u64, SolverInfo> {
+        &self.running
+    }
+
+    pub fn suspended(&self) -> &HashMap<u64, SolverInfo> {
+        &self.suspended
+    }
+
+    /// The info of the running or suspended solver
+    pub fn get(&self, id: u64) -> Option<&SolverInfo> {
+        self.running.get(&id).or_else(|| self.suspended.get(&id))
+    }
+
+    /// The ids of the solvers with the info, running solvers first
+    pub fn ids(&self, info: &SolverInfo) -> Option<&VecDeque<u64>> {
+        self.ids_by_info.get(info)
+    }
+
+    pub fn start(&mut self, id: u64, info: SolverInfo) {
+        self.stop(id);
+        self.ids_by_info
+            .entry(info.clone())
+            .or_default()
+            .push_front(id);
+        self.running.insert(id, info);
+    }
+
+    /// Moves the running solver to the suspended ones, returning whether it was running
+    pub fn suspend(&mut self, id: u64) -> bool {
+        let Some(info) = self.running.remove(&id) else {
+            return false;
+        };
+        if let Some(ids) = self.ids_by_info.get_mut(&info) {
+            ids.retain(|other| *other != id);
+            ids.push_back(id);
+        }
+        self.suspended.insert(id, info);
+        true
+    }
+
+    /// Moves the suspended solver to the running ones, returning whether it was suspended
+    pub fn resume(&mut self, id: u64) -> bool {
+        let Some(info) = self.suspended.remove(&id) else {
+            return false;
+        };
+        if let Some(ids) = self.ids_by_info.get_mut(&info) {
+            ids.retain(|other| *other != id);
+            ids.push_front(id);
+        }
+        self.running.insert(id, info);
+        true
+    }
+
+    /// Removes the running or suspended solver
+    pub fn stop(&mut self, id: u64) -> Option<SolverInfo> {
+        let info = self
+            .running
+            .remove(&id)
+            .or_else(|| self.suspended.remove(&id))?;
+        if let Some(ids) = self.ids_by_info.get_mut(&info) {
+            ids.retain(|other| *other != id);
+            if ids.is_empty() {
+                self.ids_by_info.remove(&info);
+            }
+        }
+        Some(info)
+    }
+
+    /// Removes the solvers for which the predicate is false
+    pub fn retain(&mut self, mut keep: impl FnMut(u64) -> bool) {
+        let removed: Vec<u64> = self
+            .running
+            .keys()
+            .chain(self.suspended.keys())
+            .copied()
+            .filter(|id| !keep(*id))
+            .collect();
+        for id in removed {
+            self.stop(id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(name: &str) -> SolverInfo {
+        SolverInfo::new(name.to_owned(), 1)
+    }
+
+    /// Checks that the index contains exactly the running and suspended solvers, running solvers first
+    fn assert_index_consistent(states: &SolverStates) {
+        let indexed: usize = states.ids_by_info.values().map(VecDeque::len).sum();
+        assert_eq!(indexed, states.running.len() + states.suspended.len());
+
+        for (info, ids) in &states.ids_by_info {
+            assert!(!ids.is_empty(), "empty index entry for {info:?}");
+            let running = ids.iter().take_while(|id| states.running.contains_key(id));
+            let suspended = ids.iter().skip(running.count());
+            for id in suspended {
+                assert_eq!(states.suspended.get(id), Some(info), "solver {id}");
+            }
+            for id in ids {
+                assert_eq!(states.get(*id), Some(info), "solver {id}");
+            }
+        }
+    }
+
+    fn ids(states: &SolverStates, name: &str) -> Vec<u64> {
+        states
+            .ids(&info(name))
+            .map(|ids| ids.iter().copied().collect())
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn test_index_through_the_lifecycle() {
+        let mut states = SolverStates::default();
+
+        states.start(0, info("gecode"));
+        states.start(1, info("gecode"));
+        states.start(2, info("chuffed"));
+        assert_index_consistent(&states);
+        assert_eq!(ids(&states, "gecode").len(), 2);
+
+        assert!(states.suspend(1));
+        assert!(!states.suspend(1));
+        assert_index_consistent(&states);
+        assert_eq!(ids(&states, "gecode"), [0, 1]);
+
+        assert!(states.suspend(0));
+        assert!(states.resume(1));
+        assert!(!states.resume(1));
+        assert_index_consistent(&states);
+        assert_eq!(ids(&states, "gecode"), [1, 0]);
+
+        assert_eq!(states.stop(2), Some(info("chuffed")));
+        assert_eq!(states.stop(2), None);
+        assert_index_consistent(&states);
+        assert!(states.ids(&info("chuffed")).is_none());
+
+        assert_eq!(states.stop(0), Some(info("gecode")));
+        assert_eq!(states.stop(1), Some(info("gecode")));
+        assert_index_consistent(&states);
+        assert!(states.ids_by_info.is_empty());
+    }
+
+    #[test]
+    fn test_restarting_an_id_replaces_its_info() {
+        let mut states = SolverStates::default();
+        states.start(0, info("gecode"));
+        states.suspend(0);
+        states.start(0, info("chuffed"));
+
+        assert_index_consistent(&states);
+        assert!(states.ids(&info("gecode")).is_none());
+        assert_eq!(ids(&states, "chuffed"), [0]);
+        assert!(states.suspended().is_empty());
+    }
+
+    #[test]
+    fn test_retain_removes_from_the_index() {
+        let mut states = SolverStates::default();
+        states.start(0, info("gecode"));
+        states.start(1, info("gecode"));
+        states.start(2, info("chuffed"));
+        states.suspend(2);
+
+        states.retain(|id| id == 1);
+
+        assert_index_consistent(&states);
+        assert_eq!(ids(&states, "gecode"), [1]);
+        assert!(states.ids(&info("chuffed")).is_none());
+        assert!(states.suspended().is_empty());
+    }
+}
@@ -9,6 +9,10 @@ use tokio_util::sync::CancellationToken;
 pub enum SignalEvent {
     Suspend,
     Resume,
+    /// Print the status of the solve to stderr
+    Status,
+    /// Read the static schedule file again and apply it
+    ReloadSchedule,
 }
 
 pub fn spawn_signal_handler(
@@ -35,6 +39,8 @@ pub fn spawn_signal_handler(
         let mut sigtstp = register_signal!(SignalKind::from_raw(libc::SIGTSTP));
         let mut sigcont = register_signal!(SignalKind::from_raw(libc::SIGCONT));
         let mut sigquit = register_signal!(SignalKind::quit());
+        let mut sigusr1 = register_signal!(SignalKind::user_defined1());
+        let mut sigusr2 = register_signal!(SignalKind::user_defined2());
 
         loop {
             tokio::select! {
@@ -60,6 +66,12 @@ pub fn spawn_signal_handler(
                 _ = sigcont.recv() => {
                     let _ = tx.send(SignalEvent::Resume);
                 }
+                _ = sigusr1.recv() => {
+                    let _ = tx.send(SignalEvent::Status);
+                }
+                _ = sigusr2.recv() => {
+                    let _ = tx.send(SignalEvent::ReloadSchedule);
+                }
             }
         }
     });
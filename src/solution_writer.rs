@@ -0,0 +1,144 @@
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use tokio::fs::{File, OpenOptions};
+use tokio::io::{AsyncWriteExt, BufWriter};
+use tokio::sync::Mutex;
+
+use crate::args::{OutputMode, RunArgs};
+use crate::exit_code::ExitCode;
+use crate::logging;
+use crate::solver_output::{Solution, Status};
+
+/// Writes the solution and status lines to stdout, the output file or both.
+/// It is shared by the portfolio and the backup solver, such that the output file is only truncated once.
+#[derive(Clone)]
+pub struct SolutionWriter {
+    file: Option<Arc<Mutex<BufWriter<File>>>>,
+    stdout: bool,
+    outcome: Arc<std::sync::Mutex<Outcome>>,
+}
+
+/// What was written, from which the exit code is derived
+#[derive(Default)]
+struct Outcome {
+    found_solution: bool,
+    status: Option<Status>,
+}
+
+impl SolutionWriter {
+    pub async fn new(args: &RunArgs) -> std::io::Result<Self> {
+        let file = match &args.output_file {
+            Some(path) => Some(
+                OpenOptions::new()
+                    .create(true)
+                    .write(true)
+                    .truncate(true)
+                    .open(path)
+                    .await?,
+            ),
+            None => None,
+        };
+
+        Ok(Self {
+            stdout: file.is_none() || args.tee_output,
+            file: file.map(|file| Arc::new(Mutex::new(BufWriter::new(file)))),
+            outcome: Default::default(),
+        })
+    }
+
+    pub async fn write_solution(&self, solution: &Solution, output_mode: OutputMode) {
+        self.outcome().found_solution = true;
+        self.write_line(&solution.to_output_string(output_mode))
+            .await;
+    }
+
+    pub async fn write_status(&self, status: Status, output_mode: OutputMode) {
+        let line = status.to_output_string(output_mode);
+        self.outcome().status = Some(status);
+        self.write_line(&line).await;
+    }
+
+    /// The exit code for the solutions and the last status that were written
+    pub fn exit_code(&self) -> ExitCode {
+        let outcome = self.outcome();
+        ExitCode::new(outcome.status.as_ref(), outcome.found_solution)
+    }
+
+    fn outcome(&self) -> std::sync::MutexGuard<'_, Outcome> {
+        self.outcome.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Writes the line and flushes it, such that it is not lost when the process is killed
+    pub async fn write_line(&self, line: &str) {
+        if self.stdout {
+            println!("{line}");
+            let _ = std::io::stdout().flush();
+        }
+
+        if let Some(file) = &self.file {
+            let mut file = file.lock().await;
+            let result = async {
+                file.write_all(line.as_bytes()).await?;
+                file.write_all(b"\n").await?;
+                file.flush().await
+            }
+            .await;
+            if let Err(e) = result {
+                logging::error_msg!("Failed to write to the output file: {e}");
+            }
+        }
+    }
+}
+
+/// Keeps the best solution in a file, e.g. to warm-start a later run or to survive a crash
+pub struct SolutionFile {
+    path: PathBuf,
+    on_exit_only: bool,
+    /// The solution that is written at exit when `on_exit_only` is set
+    pending: Option<String>,
+}
+
+impl SolutionFile {
+    pub fn new(path: PathBuf, on_exit_only: bool) -> Self {
+        Self {
+            path,
+            on_exit_only,
+            pending: None,
+        }
+    }
+
+    /// Called for every new best solution
+    pub async fn update(&mut self, solution: &str) {
+        if self.on_exit_only {
+            self.pending = Some(solution.to_owned());
+        } else {
+            self.write(solution).await;
+        }
+    }
+
+    /// Called once when the solve ends
+    pub async fn finish(&mut self) {
+        if let Some(solution) = self.pending.take() {
+            self.write(&solution).await;
+        }
+    }
+
+    /// Writes to a temporary file that is renamed over the file, such that the file never holds a partial solution
+    async fn write(&self, solution: &str) {
+        let mut tmp_path = self.path.clone().into_os_string();
+        tmp_path.push(".tmp");
+        let result = async {
+            tokio::fs::write(&tmp_path, solution).await?;
+            tokio::fs::rename(&tmp_path, &self.path).await
+        }
+        .await;
+        if let Err(e) = result {
+            logging::error_msg!(
+                "Failed to write the solution file '{}': {e}",
+                self.path.display()
+            );
+        }
+    }
+}
@@ -65,6 +65,8 @@ impl Solver {
         let id = Self::string_from_json("id", &mut object)?.to_lowercase();
         Ok(Self {
             executable: Self::executable_from_json(&mut object).transpose()?,
+            version: Self::optional_string_from_json("version", &mut object).transpose()?,
+            mznlib: Self::optional_string_from_json("mznlib", &mut object).transpose()?,
             input_type: Self::input_type_from_json(&id, &mut object)?,
             supported_std_flags: Self::std_flags_from_json(&id, &mut object)?,
             id,
@@ -99,6 +101,19 @@ impl Solver {
         Ok(s)
     }
 
+    fn optional_string_from_json(
+        field_name: &str,
+        object: &mut Map<String, Value>,
+    ) -> Option<SolverParseResult<String>> {
+        object.remove(field_name).map(|value| match value {
+            Value::String(s) => Ok(s),
+            _ => Err(SolverParseError::FieldNotAString(
+                field_name.to_string(),
+                value,
+            )),
+        })
+    }
+
     fn array_from_json(
         field_name: &str,
         object: &mut Map<String, Value>,
@@ -258,6 +273,14 @@ impl Solver {
         self.executable.as_ref()
     }
 
+    pub fn version(&self) -> Option<&str> {
+        self.version.as_deref()
+    }
+
+    pub fn mznlib(&self) -> Option<&str> {
+        self.mznlib.as_deref()
+    }
+
     pub fn supported_std_flags(&self) -> &SupportedStdFlags {
         &self.supported_std_flags
     }
@@ -276,3 +299,61 @@ pub enum Error {
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A sample of the `minizinc --solvers-json` output
+    const SOLVERS_JSON: &str = include_str!("../../tests/data/solvers.json");
+
+    fn sample_solvers() -> Solvers {
+        Solvers::from_json(serde_json::from_str(SOLVERS_JSON).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_version_is_parsed() {
+        let solvers = sample_solvers();
+        let gecode = solvers.get_by_id("org.gecode.gecode").unwrap();
+        assert_eq!(gecode.version(), Some("6.3.0"));
+        assert_eq!(gecode.mznlib(), Some("/usr/share/minizinc/gecode"));
+    }
+
+    #[test]
+    fn test_missing_version_is_none() {
+        let solvers = sample_solvers();
+        let custom = solvers.get_by_id("org.example.custom").unwrap();
+        assert_eq!(custom.version(), None);
+        assert_eq!(custom.mznlib(), None);
+    }
+
+    #[test]
+    fn test_unsupported_input_types_are_skipped() {
+        let solvers = sample_solvers();
+        assert_eq!(solvers.iter().count(), 2);
+        assert!(solvers.get_by_id("org.minizinc.globalizer").is_none());
+    }
+
+    #[test]
+    fn test_version_must_be_a_string() {
+        let json = serde_json::json!({
+            "id": "org.gecode.gecode",
+            "version": 6,
+            "stdFlags": [],
+            "inputType": "FZN"
+        });
+        assert!(matches!(
+            Solver::from_json(json),
+            Err(SolverParseError::FieldNotAString(field, _)) if field == "version"
+        ));
+    }
+
+    #[test]
+    fn test_version_survives_the_cache() {
+        let solvers = sample_solvers();
+        let cached: Solvers =
+            serde_json::from_str(&serde_json::to_string(&solvers).unwrap()).unwrap();
+        let gecode = cached.get_by_id("gecode").unwrap();
+        assert_eq!(gecode.version(), Some("6.3.0"));
+    }
+}
@@ -30,6 +30,11 @@ pub async fn load(mode: &SolverConfigMode, minizinc_exe: &Path) -> Solvers {
 pub struct Solver {
     id: String,
     executable: Option<Executable>,
+    /// Absent from some solver configuration files
+    version: Option<String>,
+    /// The solver library of the solver, which is absent for solvers without their own globals
+    #[serde(default)]
+    mznlib: Option<String>,
     supported_std_flags: SupportedStdFlags,
     input_type: SolverInputType,
 }
@@ -63,9 +68,22 @@ impl Solvers {
         self.0.iter()
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Falls back to matching the last component of the ids if no id matches exactly,
+    /// such that e.g. `gecode` finds `org.gecode.gecode`.
     pub fn get_by_id(&self, name: &str) -> Option<&Solver> {
         let lowered_id = name.to_lowercase();
-        self.0.iter().find(|solver| solver.id == lowered_id)
+        self.0
+            .iter()
+            .find(|solver| solver.id == lowered_id)
+            .or_else(|| {
+                self.0
+                    .iter()
+                    .find(|solver| solver.id.rsplit('.').next() == Some(lowered_id.as_str()))
+            })
     }
 }
 
@@ -76,3 +94,57 @@ impl Executable {
         cmd
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solvers(ids: &[&str]) -> Solvers {
+        Solvers(
+            ids.iter()
+                .map(|id| Solver {
+                    id: (*id).to_owned(),
+                    executable: None,
+                    version: None,
+                    mznlib: None,
+                    supported_std_flags: SupportedStdFlags::default(),
+                    input_type: SolverInputType::Fzn,
+                })
+                .collect(),
+        )
+    }
+
+    fn found_id(solvers: &Solvers, name: &str) -> Option<String> {
+        solvers.get_by_id(name).map(|solver| solver.id.clone())
+    }
+
+    #[test]
+    fn test_get_by_id_matches_the_full_id() {
+        let solvers = solvers(&["org.gecode.gecode", "org.chuffed.chuffed"]);
+        assert_eq!(
+            found_id(&solvers, "org.chuffed.chuffed").as_deref(),
+            Some("org.chuffed.chuffed")
+        );
+        assert_eq!(
+            found_id(&solvers, "Org.Gecode.Gecode").as_deref(),
+            Some("org.gecode.gecode")
+        );
+    }
+
+    #[test]
+    fn test_get_by_id_falls_back_to_the_last_component() {
+        let solvers = solvers(&["org.gecode.gecode", "org.chuffed.chuffed"]);
+        assert_eq!(
+            found_id(&solvers, "gecode").as_deref(),
+            Some("org.gecode.gecode")
+        );
+        assert_eq!(found_id(&solvers, "gecode.gecode"), None);
+        assert_eq!(found_id(&solvers, "cp-sat"), None);
+    }
+
+    #[test]
+    fn test_get_by_id_prefers_an_exact_match() {
+        let solvers = solvers(&["org.example.gecode", "gecode"]);
+        assert_eq!(found_id(&solvers, "gecode").as_deref(), Some("gecode"));
+    }
+}
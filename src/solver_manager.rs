@@ -1,13 +1,18 @@
-use crate::args::RunArgs;
-use crate::insert_objective::ObjectiveInserter;
-use crate::model_parser::{ModelParseError, ObjectiveType, ObjectiveValue, get_objective_type};
+use crate::args::{OutputMode, RunArgs};
+use crate::cgroup::{self, Cgroups};
+use crate::insert_objective::{BoundMode, ObjectiveInserter};
+use crate::metrics::{self, Metrics, SolverMetrics};
+use crate::model_parser::{
+    ModelParseError, ObjectiveDomain, ObjectiveType, ObjectiveValue, get_objective_type,
+};
 use crate::mzn_to_fzn::compilation_manager::{self, CompilationManager};
 use crate::process_tree::{
     get_process_tree_memory, recursive_force_kill, send_signals_to_process_tree,
 };
-use crate::scheduler::ScheduleElement;
+use crate::scheduler::{ScheduleElement, SolverInfo};
+use crate::solution_writer::{SolutionFile, SolutionWriter};
 use crate::solver_config::SolverInputType;
-use crate::solver_output::{Output, Solution, Status};
+use crate::solver_output::{Output, Solution, Status, comment_output_string};
 use crate::{logging, mzn_to_fzn, solver_config, solver_output};
 use async_tempfile::TempFile;
 use futures::future::join_all;
@@ -18,14 +23,16 @@ use nix::sys::signal::Signal;
 #[cfg(target_os = "linux")]
 use nix::unistd;
 use std::collections::{BTreeSet, HashMap, HashSet};
-use std::io::Write;
-use std::path::Path;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::{Path, PathBuf};
 use std::process::Stdio;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
 use sysinfo::System;
-use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
 use tokio::process::{Child, Command};
-use tokio::sync::{Mutex, RwLock, mpsc};
+use tokio::sync::{Mutex, mpsc, watch};
 use tokio::task::JoinHandle;
 use tokio_util::sync::CancellationToken;
 
@@ -43,6 +50,11 @@ pub enum Error {
     ModelParse(#[from] ModelParseError),
     #[error("failed to convert MiniZinc (mzn) to FlatZinc (fzn) format")]
     FznConversion(#[from] mzn_to_fzn::ConversionError),
+    #[error("FZN compilation failed for solver '{solver}'")]
+    CompilationFailed {
+        solver: String,
+        source: compilation_manager::WaitForError,
+    },
     #[error("failed to retrieve system cores")]
     CPUCoresRetrieval(String),
     #[error("could not set solver to a specific core")]
@@ -51,47 +63,223 @@ pub enum Error {
     ExecutableMissingForJsonSolver(String),
     #[error("piping failed for process: {0}")]
     Pipe(String),
+    #[error("failed to read the hint file '{path}'")]
+    HintFile {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
     #[error("task join failed")]
     JoinError(#[from] tokio::task::JoinError),
     #[error("conversion was cancelled")]
     MznToFzn(#[from] mzn_to_fzn::Error),
+    #[error("failed to persist the solver metrics")]
+    Metrics(#[from] metrics::Error),
 }
 pub type Result<T> = std::result::Result<T, Error>;
 
 #[derive(Debug)]
 enum Msg {
-    Solution(Solution),
-    Status(Status),
+    /// The name of the solver that found the solution, and the solution
+    Solution(String, Solution),
+    /// The status and the id of the solver that reported it
+    Status(u64, Status),
 }
 
 #[derive(Clone)]
 struct SolverProcess {
     pid: u32,
+    /// The solver name, prefixed with the AI session in the A/B test mode
+    metrics_key: String,
+    /// How often the solver was restarted after it crashed
+    restart_count: u32,
     best_objective: Option<ObjectiveValue>,
+    /// When the solver last found a better objective, or was started or resumed
+    last_improved_at: Instant,
+    metrics: SolverMetrics,
+    force_kills: ForceKills,
+    metrics_recorder: MetricsRecorder,
+    /// The cgroup that limits the memory of the solver, which is removed once it has been killed
+    cgroup: Option<PathBuf>,
 }
 
 impl Drop for SolverProcess {
     fn drop(&mut self) {
-        let _ = send_signals_to_process_tree(self.pid, vec![Signal::SIGTERM, Signal::SIGCONT]);
+        // A suspended (SIGSTOP) process does not act on any signal other than SIGKILL until it is continued,
+        // so continue it first, such that it handles the SIGTERM right away
+        let _ = send_signals_to_process_tree(self.pid, vec![Signal::SIGCONT, Signal::SIGTERM]);
+        self.metrics_recorder
+            .record(&self.metrics_key, &self.metrics);
         let pid_clone = self.pid;
-
-        std::thread::spawn(move || {
-            let _ = recursive_force_kill(pid_clone);
+        let cancellation_token = self.force_kills.cancellation_token.clone();
+        let grace_period = self.force_kills.grace_period;
+        let cgroup = self.cgroup.take();
+
+        let handle = std::thread::spawn(move || {
+            let _ = recursive_force_kill(pid_clone, grace_period, &cancellation_token);
+            if let Some(cgroup) = cgroup
+                && let Err(e) = cgroup::remove(&cgroup)
+            {
+                logging::error!(e.into());
+            }
         });
+        self.force_kills.push(handle);
+    }
+}
+
+/// Aborts the task when dropped, such that it does not outlive the handler that would otherwise await it.
+/// Aborting the pipe closes both ends of it, so the connected processes exit with a broken pipe or on EOF.
+struct AbortOnDrop(tokio::task::AbortHandle);
+
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+/// Keeps track of the force kills that are still pending, such that they can be awaited before exiting.
+#[derive(Clone)]
+struct ForceKills {
+    handles: Arc<std::sync::Mutex<Vec<std::thread::JoinHandle<()>>>>,
+    /// When cancelled, the pending force kills skip their grace period
+    cancellation_token: CancellationToken,
+    /// How long a stopped solver is given to exit after `SIGTERM` before it is killed with `SIGKILL`
+    grace_period: Duration,
+}
+
+impl ForceKills {
+    fn new(cancellation_token: CancellationToken, grace_period: Duration) -> Self {
+        Self {
+            handles: Default::default(),
+            cancellation_token,
+            grace_period,
+        }
+    }
+
+    fn push(&self, handle: std::thread::JoinHandle<()>) {
+        let mut handles = self.handles.lock().unwrap_or_else(|e| e.into_inner());
+        handles.retain(|handle| !handle.is_finished());
+        handles.push(handle);
+    }
+
+    fn take(&self) -> Vec<std::thread::JoinHandle<()>> {
+        std::mem::take(&mut *self.handles.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+}
+
+/// The runtime of the processes of a solver with a time budget, summed over its restarts. The processes of an
+/// overlap restart run at the same time, so the time is only counted once while any of them is running.
+#[derive(Debug, Default)]
+struct BudgetUsage {
+    used: Duration,
+    running: usize,
+    running_since: Option<Instant>,
+}
+
+impl BudgetUsage {
+    fn start(&mut self, now: Instant) {
+        if self.running == 0 {
+            self.running_since = Some(now);
+        }
+        self.running += 1;
+    }
+
+    fn stop(&mut self, now: Instant) {
+        self.running = self.running.saturating_sub(1);
+        if self.running == 0
+            && let Some(since) = self.running_since.take()
+        {
+            self.used += now.saturating_duration_since(since);
+        }
+    }
+
+    fn used(&self, now: Instant) -> Duration {
+        self.used
+            + self
+                .running_since
+                .map_or(Duration::ZERO, |since| now.saturating_duration_since(since))
+    }
+
+    /// Starts a process of the solver and returns how much of the budget it may still use
+    fn start_remaining(&mut self, budget: Duration, now: Instant) -> Duration {
+        let remaining = budget.saturating_sub(self.used(now));
+        self.start(now);
+        remaining
     }
 }
 
+/// Collects the metrics of the stopped solvers, such that they are kept when a solver is restarted.
+#[derive(Clone)]
+struct MetricsRecorder {
+    metrics: Arc<std::sync::Mutex<Metrics>>,
+    objective_type: ObjectiveType,
+    /// The start of the run, from which the time to the first solution is measured
+    started_at: Instant,
+}
+
+impl MetricsRecorder {
+    fn new(objective_type: ObjectiveType, started_at: Instant) -> Self {
+        Self {
+            metrics: Default::default(),
+            objective_type,
+            started_at,
+        }
+    }
+
+    fn record(&self, metrics_key: &str, solver_metrics: &SolverMetrics) {
+        let mut metrics = self.metrics.lock().unwrap_or_else(|e| e.into_inner());
+        metrics
+            .entry(metrics_key.to_owned())
+            .or_default()
+            .merge(solver_metrics, self.objective_type);
+    }
+
+    fn snapshot(&self) -> Metrics {
+        self.metrics
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+}
+
+/// A request to start a crashed solver again
+struct Restart {
+    elem: ScheduleElement,
+    cancellation_token: CancellationToken,
+    restart_count: u32,
+}
+
 pub struct SolverManager {
     tx: mpsc::UnboundedSender<Msg>,
+    restart_tx: mpsc::UnboundedSender<Restart>,
+    max_solver_restarts: u32,
     solver_processes: Arc<Mutex<HashMap<u64, SolverProcess>>>,
     current_solvers: Arc<Mutex<HashSet<u64>>>,
     args: RunArgs,
     mzn_to_fzn: Arc<CompilationManager>,
-    best_objective: Arc<RwLock<Option<ObjectiveValue>>>,
+    best_objective: Arc<watch::Sender<Option<ObjectiveValue>>>,
     solver_info: Arc<solver_config::Solvers>,
     objective_type: ObjectiveType,
+    objective_domain: ObjectiveDomain,
     solver_args: HashMap<String, Vec<String>>,
     available_cores: Arc<Mutex<BTreeSet<usize>>>, // assume that smallest ids is fastest cores, hence we use btreeset to sort the core id's
+    force_kills: ForceKills,
+    metrics_recorder: MetricsRecorder,
+    /// If set, every solver with a memory limit runs in its own cgroup
+    cgroups: Option<Arc<Cgroups>>,
+    /// The solvers that used up their time budget, which are not started again
+    exhausted_budgets: Arc<Mutex<HashSet<SolverInfo>>>,
+    /// The runtime that the solvers with a time budget used so far, such that a restart does not reset it
+    budget_usages: Arc<Mutex<HashMap<SolverInfo, BudgetUsage>>>,
+    /// The solvers whose compilation failed, which are not started again
+    failed_compilations: Arc<Mutex<HashMap<String, compilation_manager::WaitForError>>>,
+    /// The task that prints the solutions, which finishes after the cancellation once it printed the pending ones
+    receiver: Mutex<Option<JoinHandle<()>>>,
+    /// The solvers that have written at least one line of output
+    first_outputs: Arc<watch::Sender<HashSet<u64>>>,
+    /// The number of solutions that were printed
+    printed_count: Arc<AtomicU64>,
+    started_at: Instant,
 }
 
 struct PipeCommand {
@@ -109,100 +297,273 @@ struct PreparedSolver {
 }
 
 impl SolverManager {
+    #[allow(clippy::too_many_arguments)]
     pub async fn new(
         args: RunArgs,
         solver_args: HashMap<String, Vec<String>>,
+        max_solver_restarts: u32,
+        solver_kill_grace: Duration,
+        cgroups: Option<Cgroups>,
         solver_info: Arc<solver_config::Solvers>,
         compilation_manager: Arc<CompilationManager>,
+        solution_writer: SolutionWriter,
         program_cancellation_token: CancellationToken,
-    ) -> std::result::Result<Self, Error> {
-        let objective_type = get_objective_type(&args.minizinc.minizinc_exe, &args.model).await?;
+    ) -> std::result::Result<Arc<Self>, Error> {
+        let (objective_type, objective_domain) =
+            get_objective_type(&args.minizinc.minizinc_exe, &args.model).await?;
         let (tx, rx) = mpsc::unbounded_channel::<Msg>();
         let solvers = Arc::new(Mutex::new(HashMap::new()));
 
-        let best_objective: Arc<RwLock<Option<i64>>> = Arc::new(RwLock::new(None));
+        let best_objective = Arc::new(watch::Sender::new(None));
+
+        let force_kills = ForceKills::new(program_cancellation_token.clone(), solver_kill_grace);
+
+        let current_solvers: Arc<Mutex<HashSet<u64>>> = Default::default();
 
         let shared_objective = best_objective.clone();
-        tokio::spawn(async move {
+        let warn_on_unsatisfiable = args.warn_on_unsatisfiable;
+        let max_solutions = args.max_solutions;
+        let benchmark = args.benchmark;
+        let tag_solutions = args.tag_solutions;
+        let allow_duplicates = args.allow_duplicates;
+        let output_mode = args.output_mode;
+        let solution_file = args
+            .solution_file
+            .clone()
+            .map(|path| SolutionFile::new(path, args.solution_on_exit_only));
+        let current_solvers_clone = current_solvers.clone();
+        let printed_count: Arc<AtomicU64> = Default::default();
+        let printed_count_clone = printed_count.clone();
+        let restart_cancellation_token = program_cancellation_token.clone();
+        let receiver = tokio::spawn(async move {
             Self::receiver(
                 rx,
                 objective_type,
+                output_mode,
                 shared_objective,
+                warn_on_unsatisfiable,
+                max_solutions,
+                benchmark,
+                tag_solutions,
+                allow_duplicates,
+                solution_file,
+                current_solvers_clone,
+                printed_count_clone,
+                solution_writer,
                 program_cancellation_token,
             )
             .await
         });
-        let mut cores = BTreeSet::new();
-        if let Some(core_ids) = core_affinity::get_core_ids() {
-            for core in core_ids {
-                cores.insert(core.id);
+        let cores = get_available_cores()?;
+        let started_at = Instant::now();
+        let (restart_tx, restart_rx) = mpsc::unbounded_channel::<Restart>();
+
+        Ok(Arc::new_cyclic(|manager: &Weak<Self>| {
+            let manager = manager.clone();
+            tokio::spawn(async move {
+                tokio::select! {
+                    _ = restart_cancellation_token.cancelled() => {}
+                    _ = Self::restart_loop(manager, restart_rx) => {}
+                }
+            });
+
+            Self {
+                tx,
+                restart_tx,
+                max_solver_restarts,
+                solver_processes: solvers,
+                solver_info: solver_info.clone(),
+                mzn_to_fzn: compilation_manager,
+                current_solvers,
+                args,
+                best_objective,
+                objective_type,
+                objective_domain,
+                solver_args,
+                available_cores: Arc::new(Mutex::new(cores)),
+                force_kills,
+                metrics_recorder: MetricsRecorder::new(objective_type, started_at),
+                cgroups: cgroups.map(Arc::new),
+                exhausted_budgets: Default::default(),
+                budget_usages: Default::default(),
+                failed_compilations: Default::default(),
+                receiver: Mutex::new(Some(receiver)),
+                first_outputs: Arc::new(watch::Sender::new(HashSet::new())),
+                printed_count,
+                started_at,
             }
-        } else {
-            return Err(Error::CPUCoresRetrieval(
-                "Could not retrieve system cores".to_string(),
-            ));
-        }
+        }))
+    }
 
-        Ok(Self {
-            tx,
-            solver_processes: solvers,
-            solver_info: solver_info.clone(),
-            mzn_to_fzn: compilation_manager,
-            current_solvers: Default::default(),
-            args,
-            best_objective,
-            objective_type,
-            solver_args,
-            available_cores: Arc::new(Mutex::new(cores)),
-        })
+    /// Starts the crashed solvers again. The wait task of a solver cannot do this itself, since it does not
+    /// have access to the solver manager.
+    async fn restart_loop(manager: Weak<Self>, mut restart_rx: mpsc::UnboundedReceiver<Restart>) {
+        while let Some(restart) = restart_rx.recv().await {
+            let Some(manager) = manager.upgrade() else {
+                break;
+            };
+            let id = restart.elem.id;
+            if let Err(e) = manager
+                .start_solver_attempt(
+                    &restart.elem,
+                    restart.cancellation_token,
+                    restart.restart_count,
+                )
+                .await
+            {
+                logging::error!(e.into());
+                manager.current_solvers.lock().await.remove(&id);
+            }
+        }
     }
 
+    #[allow(clippy::too_many_arguments)]
     async fn receiver(
         mut rx: mpsc::UnboundedReceiver<Msg>,
         objective_type: ObjectiveType,
-        shared_objective: Arc<RwLock<Option<ObjectiveValue>>>,
+        output_mode: OutputMode,
+        shared_objective: Arc<watch::Sender<Option<ObjectiveValue>>>,
+        warn_on_unsatisfiable: bool,
+        max_solutions: Option<u64>,
+        benchmark: bool,
+        tag_solutions: bool,
+        allow_duplicates: bool,
+        mut solution_file: Option<SolutionFile>,
+        current_solvers: Arc<Mutex<HashSet<u64>>>,
+        printed_count: Arc<AtomicU64>,
+        solution_writer: SolutionWriter,
         program_cancellation_token: CancellationToken,
     ) {
         let mut objective: Option<ObjectiveValue> = None;
+        let mut unsatisfiable_solvers = HashSet::new();
+        // Different solvers can find the same solution of a satisfaction problem, which is only printed once.
+        // Only the hashes are kept, since there can be many large solutions.
+        let mut printed_solutions: HashSet<u64> = HashSet::new();
+        // In satisfaction problems, we are only interested in a single solution by default.
+        // When benchmarking, every solver runs until the time limit.
+        let max_solutions = match objective_type {
+            _ if benchmark => None,
+            ObjectiveType::Satisfy => Some(max_solutions.unwrap_or(1)),
+            _ => max_solutions,
+        };
+
+        loop {
+            // After the cancellation, the messages that were already sent are still handled, such that no solution
+            // is lost. The channel is only empty once they are all handled, since the select is biased.
+            let output = tokio::select! {
+                biased;
+                output = rx.recv() => output,
+                _ = program_cancellation_token.cancelled() => rx.try_recv().ok(),
+            };
+            let Some(output) = output else {
+                break;
+            };
 
-        while let Some(output) = rx.recv().await {
             match output {
-                Msg::Solution(Solution {
-                    solution: s,
-                    objective: Some(o),
-                }) => {
-                    if objective_type.is_better(objective, o) {
-                        objective = Some(o);
-                        {
-                            let mut guard = shared_objective.write().await;
-                            *guard = Some(o);
-                        }
-                        println!("{}", s.trim_end());
-                        let _ = std::io::stdout().flush();
+                Msg::Solution(
+                    solver_name,
+                    solution @ Solution {
+                        objective: Some(o), ..
+                    },
+                ) => {
+                    if !objective_type.is_better(objective, o) {
+                        continue;
+                    }
+                    objective = Some(o);
+                    shared_objective.send_replace(Some(o));
+                    if tag_solutions {
+                        solution_writer
+                            .write_line(&comment_output_string(
+                                &format!("from: {solver_name}"),
+                                output_mode,
+                            ))
+                            .await;
+                    }
+                    solution_writer.write_solution(&solution, output_mode).await;
+                    if let Some(solution_file) = &mut solution_file {
+                        solution_file.update(&solution.solution).await;
                     }
+                    printed_count.fetch_add(1, Ordering::Relaxed);
                 }
-                Msg::Solution(Solution {
-                    solution: s,
-                    objective: None, // is satisfaction problem
-                }) => {
-                    println!("{}", s.trim_end());
-                    let _ = std::io::stdout().flush();
-                    // In satisfaction problems, we are only interested in a single solution
+                Msg::Solution(
+                    solver_name,
+                    solution @ Solution {
+                        objective: None, // is satisfaction problem
+                        ..
+                    },
+                ) => {
+                    if !allow_duplicates
+                        && !printed_solutions.insert(solution_hash(&solution.solution))
+                    {
+                        continue;
+                    }
+                    if tag_solutions {
+                        solution_writer
+                            .write_line(&comment_output_string(
+                                &format!("from: {solver_name}"),
+                                output_mode,
+                            ))
+                            .await;
+                    }
+                    solution_writer.write_solution(&solution, output_mode).await;
+                    if let Some(solution_file) = &mut solution_file {
+                        solution_file.update(&solution.solution).await;
+                    }
+                    printed_count.fetch_add(1, Ordering::Relaxed);
+                }
+                Msg::Status(solver_id, status) if benchmark => {
+                    logging::info!("solver {solver_id} finished with status: {status}");
+                }
+                Msg::Status(solver_id, Status::Unsatisfiable) if warn_on_unsatisfiable => {
+                    logging::warning!(
+                        "solver {solver_id} reported that the problem is unsatisfiable"
+                    );
+                    unsatisfiable_solvers.insert(solver_id);
+
+                    let all_unsatisfiable = current_solvers
+                        .lock()
+                        .await
+                        .is_subset(&unsatisfiable_solvers);
+                    if all_unsatisfiable {
+                        logging::info!("all solvers reported that the problem is unsatisfiable");
+                        solution_writer
+                            .write_status(Status::Unsatisfiable, output_mode)
+                            .await;
+                        program_cancellation_token.cancel();
+                        break;
+                    }
+                }
+                Msg::Status(_, Status::AllSolutions) => {
+                    // Only the first solution of a satisfaction problem is printed, so the solutions found by
+                    // the solver were not all printed, and the terminator would be wrong here
+                    logging::info!("a solver finished with status: {}", Status::AllSolutions);
                     program_cancellation_token.cancel();
                     break;
                 }
-                Msg::Status(status) => {
+                Msg::Status(_, status) => {
                     if status != Status::Unknown {
-                        println!("{}", status.to_dzn_string());
-                        let _ = std::io::stdout().flush();
+                        logging::info!("a solver finished with status: {status}");
+                        solution_writer.write_status(status, output_mode).await;
                         program_cancellation_token.cancel();
                         break;
                     }
                 }
             }
+
+            let printed = printed_count.load(Ordering::Relaxed);
+            if max_solutions.is_some_and(|max| printed >= max) {
+                logging::info!("printed {printed} solutions, stopping");
+                program_cancellation_token.cancel();
+                break;
+            }
+        }
+
+        if let Some(solution_file) = &mut solution_file {
+            solution_file.finish().await;
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn get_solver_command(
         fzn_path: &Path,
         solver_name: &str,
@@ -210,6 +571,8 @@ impl SolverManager {
         solver_info: &solver_config::Solvers,
         minizinc_exe: &Path,
         solver_args: &HashMap<String, Vec<String>>,
+        extra_args: Option<&[String]>,
+        flags: &[String],
     ) -> Result<Command> {
         let solver = solver_info.get_by_id(solver_name);
 
@@ -232,14 +595,21 @@ impl SolverManager {
 
         cmd.arg(fzn_path);
 
-        // Apply solver-specific arguments from config
-        if let Some(args) = solver_args.get(solver_name) {
-            for arg in args {
-                cmd.arg(arg);
+        // The arguments from the config, which include the command-line flags, followed by the ones from the
+        // schedule
+        match solver_args.get(solver_name) {
+            Some(args) => {
+                cmd.args(args);
             }
-        } else {
-            logging::error_msg!("Solver '{solver_name}' does not have an arguments configuration");
+            None if extra_args.is_none() => {
+                logging::error_msg!(
+                    "Solver '{solver_name}' does not have an arguments configuration"
+                );
+            }
+            None => {}
         }
+        cmd.args(extra_args.unwrap_or_default());
+        cmd.args(flags);
 
         let supports_p_flag = solver
             .map(|solver| solver.supported_std_flags().p)
@@ -251,10 +621,13 @@ impl SolverManager {
         Ok(cmd)
     }
 
-    fn get_ozn_command(minizinc_exe: &Path, ozn_path: &Path) -> Command {
+    fn get_ozn_command(minizinc_exe: &Path, ozn_path: &Path, output_mode: OutputMode) -> Command {
         let mut cmd = Command::new(minizinc_exe);
         cmd.arg("--ozn-file");
         cmd.arg(ozn_path);
+        if output_mode.is_json_stream() {
+            cmd.arg("--json-stream");
+        }
         cmd
     }
 
@@ -262,15 +635,26 @@ impl SolverManager {
     async fn prepare_solver_process(
         solver_name: &str,
         cores: usize,
+        extra_args: Option<&[String]>,
+        flags: &[String],
+        session: Option<&str>,
+        hint_file: Option<&Path>,
         elem_id: u64,
         cancellation_token: &CancellationToken,
         mzn_to_fzn: &CompilationManager,
         solver_info: &solver_config::Solvers,
-        best_objective: &RwLock<Option<ObjectiveValue>>,
+        best_objective: &watch::Sender<Option<ObjectiveValue>>,
         objective_type: ObjectiveType,
+        bound_mode: BoundMode,
+        output_mode: OutputMode,
         minizinc_exe: &Path,
         solver_args: &HashMap<String, Vec<String>>,
         solver_processes: &Mutex<HashMap<u64, SolverProcess>>,
+        force_kills: &ForceKills,
+        metrics_recorder: &MetricsRecorder,
+        cgroups: Option<&Cgroups>,
+        failed_compilations: &Mutex<HashMap<String, compilation_manager::WaitForError>>,
+        restart_count: u32,
         #[cfg(target_os = "linux")] available_cores: &Arc<Mutex<BTreeSet<usize>>>,
         #[cfg(target_os = "linux")] pin_yuck: bool,
     ) -> std::result::Result<PreparedSolver, ()> {
@@ -284,14 +668,31 @@ impl SolverManager {
             return Err(());
         };
 
-        let Ok(conversion_paths) = conversion_paths.map_err(|e| logging::error!(e.into())) else {
-            return Err(());
+        let conversion_paths = match conversion_paths {
+            Ok(conversion_paths) => conversion_paths,
+            Err(source) => {
+                // A failed compilation fails again, so the solver is not started again
+                if !matches!(source, compilation_manager::WaitForError::Cancelled(_)) {
+                    failed_compilations
+                        .lock()
+                        .await
+                        .insert(solver_name.to_owned(), source.clone());
+                }
+                logging::error!(
+                    Error::CompilationFailed {
+                        solver: solver_name.to_owned(),
+                        source,
+                    }
+                    .into()
+                );
+                return Err(());
+            }
         };
 
         // Create ObjectiveInserter inside the spawn
-        let objective_inserter = ObjectiveInserter::new(Arc::new(solver_info.clone()));
+        let objective_inserter = ObjectiveInserter::new(Arc::new(solver_info.clone()), bound_mode);
 
-        let objective = *best_objective.read().await;
+        let objective = *best_objective.borrow();
         let (fzn_final_path, fzn_guard) = if let Some(obj) = objective {
             if let Ok(new_temp_file) = objective_inserter
                 .insert_objective(solver_name, conversion_paths.fzn(), &objective_type, obj)
@@ -312,6 +713,8 @@ impl SolverManager {
             solver_info,
             minizinc_exe,
             solver_args,
+            extra_args,
+            flags,
         )
         .map_err(|e| logging::error!(e.into())) else {
             return Err(());
@@ -321,25 +724,74 @@ impl SolverManager {
         fzn_cmd.process_group(0); // let OS give it a group process id
         fzn_cmd.stderr(Stdio::piped());
 
-        let mut ozn_cmd = Self::get_ozn_command(minizinc_exe, conversion_paths.ozn());
+        let hint = match hint_file {
+            Some(path) => match tokio::fs::read(path).await {
+                Ok(hint) => Some(hint),
+                Err(source) => {
+                    logging::error!(
+                        Error::HintFile {
+                            path: path.to_path_buf(),
+                            source
+                        }
+                        .into()
+                    );
+                    return Err(());
+                }
+            },
+            None => None,
+        };
+
+        let mut ozn_cmd = Self::get_ozn_command(minizinc_exe, conversion_paths.ozn(), output_mode);
         ozn_cmd.stdout(Stdio::piped());
         ozn_cmd.stderr(Stdio::piped());
 
         // we lock on solvers to guarantee we dont in another thread try to stop them at the same time
         let mut map = solver_processes.lock().await;
         let Ok(PipeCommand {
-            left: fzn,
+            left: mut fzn,
             right: ozn,
             pipe,
-        }) = pipe(fzn_cmd, ozn_cmd).map_err(|e| logging::error!(e.into()))
+        }) = pipe(fzn_cmd, ozn_cmd, hint).map_err(|e| logging::error!(e.into()))
         else {
             return Err(());
         };
 
-        let pid = fzn.id().expect("Child has no PID");
+        let Some(pid) = fzn.id() else {
+            drop(map);
+            logging::warning!(
+                "Solver '{solver_name}' exited immediately before its PID could be read"
+            );
+            if let Err(e) = fzn.wait().await {
+                logging::error!(Error::from(e).into());
+            }
+            if let Some(stderr) = fzn.stderr.take() {
+                Self::handle_solver_stderr(stderr, solver_name).await;
+            }
+            return Err(());
+        };
+        let cgroup = match cgroups {
+            Some(cgroups) => cgroups
+                .add_solver(elem_id, solver_name, pid)
+                .await
+                .unwrap_or_else(|e| {
+                    logging::error!(e.into());
+                    logging::warning!(
+                        "Solver '{solver_name}' runs without a cgroup, so its memory limit is not enforced by the kernel"
+                    );
+                    None
+                }),
+            None => None,
+        };
         let solver_proccess = SolverProcess {
             pid,
+            metrics_key: metrics::key(solver_name, session),
+            restart_count,
             best_objective: objective,
+            last_improved_at: Instant::now(),
+            metrics: SolverMetrics::default(),
+            force_kills: force_kills.clone(),
+            metrics_recorder: metrics_recorder.clone(),
+            cgroup,
         };
 
         map.insert(elem_id, solver_proccess);
@@ -369,7 +821,34 @@ impl SolverManager {
         })
     }
 
-    async fn start_solver(&self, elem: &ScheduleElement, cancellation_token: CancellationToken) {
+    async fn start_solver(
+        &self,
+        elem: &ScheduleElement,
+        cancellation_token: CancellationToken,
+    ) -> Result<()> {
+        self.start_solver_attempt(elem, cancellation_token, 0).await
+    }
+
+    async fn start_solver_attempt(
+        &self,
+        elem: &ScheduleElement,
+        cancellation_token: CancellationToken,
+        restart_count: u32,
+    ) -> Result<()> {
+        if self.exhausted_budgets.lock().await.contains(&elem.info) {
+            logging::info!(
+                "not starting solver '{}', since it used up its time budget",
+                elem.info.name
+            );
+            return Ok(());
+        }
+        if let Some(source) = self.failed_compilations.lock().await.get(&elem.info.name) {
+            return Err(Error::CompilationFailed {
+                solver: elem.info.name.clone(),
+                source: source.clone(),
+            });
+        }
+
         {
             self.current_solvers.lock().await.insert(elem.id); // keep track of current running/suspended solvers
         }
@@ -383,11 +862,23 @@ impl SolverManager {
         let tx = self.tx.clone();
         let available_cores = self.available_cores.clone();
         let objective_type = self.objective_type;
+        let objective_domain = self.objective_domain;
+        let bound_mode = BoundMode::new(self.args.strict_bounds);
+        let output_mode = self.args.output_mode;
         let elem = elem.clone();
         let current_solvers = self.current_solvers.clone();
         #[cfg(target_os = "linux")]
         let pin_yuck = self.args.pin_yuck;
         let best_objective = self.best_objective.clone();
+        let force_kills = self.force_kills.clone();
+        let metrics_recorder = self.metrics_recorder.clone();
+        let cgroups = self.cgroups.clone();
+        let failed_compilations = self.failed_compilations.clone();
+        let exhausted_budgets = self.exhausted_budgets.clone();
+        let budget_usages = self.budget_usages.clone();
+        let first_outputs = self.first_outputs.clone();
+        let restart_tx = self.restart_tx.clone();
+        let max_solver_restarts = self.max_solver_restarts;
 
         tokio::spawn(async move {
             let solver_name = &elem.info.name;
@@ -397,15 +888,26 @@ impl SolverManager {
             let result = Self::prepare_solver_process(
                 solver_name,
                 cores,
+                elem.info.extra_args.as_deref(),
+                &elem.info.flags,
+                elem.info.session.as_deref(),
+                elem.info.hint_file.as_deref(),
                 elem_id,
                 &cancellation_token,
                 &mzn_to_fzn,
                 &solver_info,
                 &best_objective,
                 objective_type,
+                bound_mode,
+                output_mode,
                 &minizinc_exe,
                 &solver_args,
                 &solver_processes,
+                &force_kills,
+                &metrics_recorder,
+                cgroups.as_deref(),
+                &failed_compilations,
+                restart_count,
                 #[cfg(target_os = "linux")]
                 &available_cores,
                 #[cfg(target_os = "linux")]
@@ -431,6 +933,9 @@ impl SolverManager {
 
             let solver_id = elem.id;
             let solver_name_for_wait = elem.info.name.clone();
+            let solver_name_for_stdout = elem.info.name.clone();
+            let solver_name_for_fzn_stderr = elem.info.name.clone();
+            let solver_name_for_ozn_stderr = elem.info.name.clone();
             let solvers_for_stdout = solver_processes.clone();
             let solvers_for_wait = solver_processes.clone();
             let available_cores_for_wait = available_cores.clone();
@@ -442,34 +947,72 @@ impl SolverManager {
                     pipe,
                     tx,
                     solver_id,
+                    solver_name_for_stdout,
                     solvers_for_stdout,
                     objective_type,
+                    objective_domain,
+                    output_mode,
+                    first_outputs,
                     cancellation_token_stdout,
                 )
                 .await;
             });
 
-            tokio::spawn(async move { Self::handle_solver_stderr(fzn_stderr).await });
-            tokio::spawn(async move { Self::handle_solver_stderr(ozn_stderr).await });
+            tokio::spawn(async move {
+                Self::handle_solver_stderr(fzn_stderr, &solver_name_for_fzn_stderr).await
+            });
+            tokio::spawn(async move {
+                Self::handle_solver_stderr(ozn_stderr, &solver_name_for_ozn_stderr).await
+            });
 
             tokio::spawn(async move {
                 let _keep_alive = fzn_guard;
+                let remaining_budget = match elem.info.time_budget {
+                    Some(budget) => Some(
+                        budget_usages
+                            .lock()
+                            .await
+                            .entry(elem.info.clone())
+                            .or_default()
+                            .start_remaining(budget, Instant::now()),
+                    ),
+                    None => None,
+                };
+                let time_budget = async {
+                    match remaining_budget {
+                        Some(remaining) => tokio::time::sleep(remaining).await,
+                        None => std::future::pending().await,
+                    }
+                };
 
-                tokio::select! {
+                let failed = tokio::select! {
                     result = fzn.wait() => {
                         match result {
                             Ok(status) if !status.success() => {
                                 logging::info!("Solver '{}' exited with status: {}", solver_name_for_wait, status);
+                                true
                             }
                             Err(e) => {
                                 logging::error_msg!("Error waiting for solver '{}': {}", solver_name_for_wait, e);
+                                false
                             }
-                            _ => {}
+                            _ => false,
                         }
                     }
                     _ = cancellation_token.cancelled() => {
                         logging::info!("Solver '{}' cancelled", solver_name_for_wait);
+                        false
                     }
+                    _ = time_budget => {
+                        logging::info!("Solver '{}' used up its time budget", solver_name_for_wait);
+                        exhausted_budgets.lock().await.insert(elem.info.clone());
+                        false
+                    }
+                };
+                if remaining_budget.is_some()
+                    && let Some(usage) = budget_usages.lock().await.get_mut(&elem.info)
+                {
+                    usage.stop(Instant::now());
                 }
 
                 {
@@ -479,25 +1022,55 @@ impl SolverManager {
                     }
                 }
                 logging::info!("solver exitted {solver_id}");
+                // A solver that was stopped is removed from the map before it exits, so a solver that failed and
+                // is still in the map has crashed
+                let crashed_process = solvers_for_wait.lock().await.remove(&solver_id);
+                if failed
+                    && let Some(process) = crashed_process
+                    && process.restart_count < max_solver_restarts
+                    && !cancellation_token.is_cancelled()
+                {
+                    let restart_count = process.restart_count + 1;
+                    drop(process);
+                    logging::warning!(
+                        "Solver '{}' crashed, restarting it (attempt {restart_count} of {max_solver_restarts})",
+                        solver_name_for_wait
+                    );
+                    // The solver stays in the current solvers, such that the scheduler keeps it in its state
+                    let restart = Restart {
+                        elem,
+                        cancellation_token,
+                        restart_count,
+                    };
+                    if restart_tx.send(restart).is_ok() {
+                        return;
+                    }
+                }
                 current_solvers.lock().await.remove(&solver_id);
-                let mut map = solvers_for_wait.lock().await;
-                map.remove(&solver_id);
             });
         });
+        Ok(())
     }
 
+    #[allow(clippy::too_many_arguments)]
     async fn handle_solver_stdout(
         stdout: tokio::process::ChildStdout,
         pipe: JoinHandle<std::io::Result<u64>>,
         tx: tokio::sync::mpsc::UnboundedSender<Msg>,
         solver_id: u64,
+        solver_name: String,
         solver_processes: Arc<Mutex<HashMap<u64, SolverProcess>>>,
         objective_type: ObjectiveType,
+        objective_domain: ObjectiveDomain,
+        output_mode: OutputMode,
+        first_outputs: Arc<watch::Sender<HashSet<u64>>>,
         cancellation_token: CancellationToken,
     ) {
+        let _pipe_guard = AbortOnDrop(pipe.abort_handle());
         let reader = BufReader::new(stdout);
         let mut lines = reader.lines();
-        let mut parser = solver_output::Parser::new(objective_type);
+        let mut parser =
+            solver_output::OutputParser::new(output_mode, objective_type, objective_domain);
 
         let mut local_best: Option<ObjectiveValue> = {
             let map = solver_processes.lock().await;
@@ -518,6 +1091,11 @@ impl SolverManager {
                 }
                 _ = cancellation_token.cancelled() => break,
             };
+            if !line.trim().is_empty() && !first_outputs.borrow().contains(&solver_id) {
+                first_outputs.send_modify(|ids| {
+                    ids.insert(solver_id);
+                });
+            }
             let output = match parser.next_line(&line) {
                 Ok(o) => o,
                 Err(e) => {
@@ -530,14 +1108,28 @@ impl SolverManager {
                 continue;
             };
 
+            if let Output::Solution(_) = &output {
+                let mut map = solver_processes.lock().await;
+                if let Some(state) = map.get_mut(&solver_id) {
+                    state.metrics.solution_count += 1;
+                    if state.metrics.time_to_first_solution.is_none() {
+                        let elapsed = state.metrics_recorder.started_at.elapsed();
+                        state.metrics.time_to_first_solution = Some(elapsed.as_secs_f64());
+                    }
+                }
+            }
+
             let msg = match output {
                 Output::Solution(Solution {
                     solution: s,
                     objective: None,
-                }) => Msg::Solution(Solution {
-                    solution: s,
-                    objective: None,
-                }),
+                }) => Msg::Solution(
+                    solver_name.clone(),
+                    Solution {
+                        solution: s,
+                        objective: None,
+                    },
+                ),
                 Output::Solution(Solution {
                     solution: s,
                     objective: Some(o),
@@ -547,14 +1139,20 @@ impl SolverManager {
                         let mut map = solver_processes.lock().await;
                         if let Some(state) = map.get_mut(&solver_id) {
                             state.best_objective = local_best;
+                            state.last_improved_at = Instant::now();
+                            state.metrics.improving_solutions += 1;
+                            state.metrics.best_objective = local_best;
                         }
                     }
-                    Msg::Solution(Solution {
-                        solution: s,
-                        objective: Some(o),
-                    })
+                    Msg::Solution(
+                        solver_name.clone(),
+                        Solution {
+                            solution: s,
+                            objective: Some(o),
+                        },
+                    )
                 }
-                Output::Status(status) => Msg::Status(status),
+                Output::Status(status) => Msg::Status(solver_id, status),
             };
 
             if let Err(e) = tx.send(msg) {
@@ -563,6 +1161,8 @@ impl SolverManager {
             }
         }
 
+        first_outputs.send_if_modified(|ids| ids.remove(&solver_id));
+
         match pipe.await {
             Ok(_) => {}
             Err(e) => {
@@ -571,7 +1171,8 @@ impl SolverManager {
         }
     }
 
-    async fn handle_solver_stderr(stderr: tokio::process::ChildStderr) {
+    /// Logs the stderr of the solver, prefixed with its name, since the output of all solvers is interleaved
+    async fn handle_solver_stderr(stderr: tokio::process::ChildStderr, solver_name: &str) {
         let reader = BufReader::new(stderr);
         let mut lines = reader.lines();
 
@@ -579,29 +1180,51 @@ impl SolverManager {
             logging::error_msg!("Error reading solver stderr: {}", e);
             None
         }) {
-            logging::error_msg!("Solver stderr: {}", line);
+            logging::error_msg!("[{solver_name}] Solver stderr: {line}");
         }
     }
 
+    /// Starts the solvers in the background. The solvers whose compilation failed before are skipped and returned
+    /// as errors.
     pub async fn start_solvers(
         &self,
         schedule: &[ScheduleElement],
         cancellation_token: CancellationToken,
-    ) {
+    ) -> std::result::Result<(), Vec<Error>> {
+        // Start all compilations up front, so each solver only waits for its own compilation
+        self.mzn_to_fzn
+            .start_many(schedule.iter().map(|elem| elem.info.name.clone()))
+            .await;
+
         let futures = schedule
             .iter()
             .map(|elem| self.start_solver(elem, cancellation_token.clone()));
-        join_all(futures).await;
+        let errors: Vec<Error> = join_all(futures)
+            .await
+            .into_iter()
+            .filter_map(|result| result.err())
+            .collect();
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
     }
 
     async fn send_signals_to_solver(
         signals: Vec<Signal>,
         id: u64,
-        solvers_guard: tokio::sync::MutexGuard<'_, HashMap<u64, SolverProcess>>,
+        solver_processes: &Mutex<HashMap<u64, SolverProcess>>,
     ) -> Result<()> {
-        let pid = match solvers_guard.get(&id) {
-            Some(state) => state.pid,
-            None => return Err(Error::InvalidSolver(format!("Solver {id} not running"))),
+        // The lock is released before sending the signals, such that it is not held during the syscalls
+        let pid = solver_processes
+            .lock()
+            .await
+            .get(&id)
+            .map(|state| state.pid);
+        let Some(pid) = pid else {
+            return Err(Error::InvalidSolver(format!("Solver {id} not running")));
         };
         send_signals_to_process_tree(pid, signals)
             .map_err(|e| Error::InvalidSolver(format!("Failed to send signals: {}", e)))
@@ -610,12 +1233,19 @@ impl SolverManager {
     async fn send_signals_to_solvers(
         signals: Vec<Signal>,
         ids: &[u64],
-        solver_processes: tokio::sync::MutexGuard<'_, HashMap<u64, SolverProcess>>,
+        solver_processes: &Mutex<HashMap<u64, SolverProcess>>,
     ) -> std::result::Result<(), Vec<Error>> {
-        let futures = ids.iter().map(async |id| {
-            let pid = match solver_processes.get(id) {
-                Some(state) => state.pid,
-                None => return Err(Error::InvalidSolver(format!("Solver {id} not running"))),
+        // The lock is released before sending the signals, such that it is not held during the syscalls
+        let pids: Vec<(u64, Option<u32>)> = {
+            let solver_processes = solver_processes.lock().await;
+            ids.iter()
+                .map(|id| (*id, solver_processes.get(id).map(|state| state.pid)))
+                .collect()
+        };
+
+        let futures = pids.into_iter().map(async |(id, pid)| {
+            let Some(pid) = pid else {
+                return Err(Error::InvalidSolver(format!("Solver {id} not running")));
             };
             send_signals_to_process_tree(pid, signals.clone())
                 .map_err(|e| Error::InvalidSolver(format!("Failed to send signals: {}", e)))
@@ -635,20 +1265,16 @@ impl SolverManager {
         solver_processes: Arc<Mutex<HashMap<u64, SolverProcess>>>,
         signals: Vec<Signal>,
     ) -> std::result::Result<(), Vec<Error>> {
-        let solvers_guard = solver_processes.lock().await;
-        let ids: Vec<u64> = { solvers_guard.keys().cloned().collect() };
-        Self::send_signals_to_solvers(signals, &ids, solvers_guard).await
+        let ids: Vec<u64> = solver_processes.lock().await.keys().cloned().collect();
+        Self::send_signals_to_solvers(signals, &ids, &solver_processes).await
     }
 
-    #[allow(dead_code)]
     pub async fn suspend_solver(&self, id: u64) -> std::result::Result<(), Error> {
-        let solvers_guard = self.solver_processes.lock().await;
-        Self::send_signals_to_solver(vec![Signal::SIGSTOP], id, solvers_guard).await
+        Self::send_signals_to_solver(vec![Signal::SIGSTOP], id, &self.solver_processes).await
     }
 
     pub async fn suspend_solvers(&self, ids: &[u64]) -> std::result::Result<(), Vec<Error>> {
-        let solvers_guard = self.solver_processes.lock().await;
-        Self::send_signals_to_solvers(vec![Signal::SIGSTOP], ids, solvers_guard).await
+        Self::send_signals_to_solvers(vec![Signal::SIGSTOP], ids, &self.solver_processes).await
     }
 
     #[allow(dead_code)]
@@ -657,15 +1283,33 @@ impl SolverManager {
             .await
     }
 
-    #[allow(dead_code)]
     pub async fn resume_solver(&self, id: u64) -> std::result::Result<(), Error> {
-        let solvers_guard = self.solver_processes.lock().await;
-        Self::send_signals_to_solver(vec![Signal::SIGCONT], id, solvers_guard).await
+        self.reset_last_improvements(&[id]).await;
+        Self::send_signals_to_solver(vec![Signal::SIGCONT], id, &self.solver_processes).await
     }
 
     pub async fn resume_solvers(&self, ids: &[u64]) -> std::result::Result<(), Vec<Error>> {
-        let solvers_guard = self.solver_processes.lock().await;
-        Self::send_signals_to_solvers(vec![Signal::SIGCONT], ids, solvers_guard).await
+        self.reset_last_improvements(ids).await;
+        Self::send_signals_to_solvers(vec![Signal::SIGCONT], ids, &self.solver_processes).await
+    }
+
+    /// A resumed solver gets the full stall timeout again, since it could not improve while it was suspended
+    async fn reset_last_improvements(&self, ids: &[u64]) {
+        let now = Instant::now();
+        let mut map = self.solver_processes.lock().await;
+        for id in ids {
+            if let Some(state) = map.get_mut(id) {
+                state.last_improved_at = now;
+            }
+        }
+    }
+
+    /// When each of the given solvers last found a better objective, or was started or resumed
+    pub async fn last_improvements(&self, ids: &[u64]) -> HashMap<u64, Instant> {
+        let map = self.solver_processes.lock().await;
+        ids.iter()
+            .filter_map(|id| map.get(id).map(|state| (*id, state.last_improved_at)))
+            .collect()
     }
 
     #[allow(dead_code)]
@@ -733,7 +1377,6 @@ impl SolverManager {
         Self::_stop_solvers(self.solver_processes.clone(), ids).await
     }
 
-    #[allow(dead_code)]
     pub async fn stop_all_solvers(&self) -> std::result::Result<(), Vec<Error>> {
         Self::_stop_all_solvers(self.solver_processes.clone()).await
     }
@@ -742,6 +1385,11 @@ impl SolverManager {
         self.current_solvers.lock().await.clone()
     }
 
+    /// The number of solvers that are running, suspended or waiting for their compilation
+    pub async fn active_solver_count(&self) -> usize {
+        self.current_solvers.lock().await.len()
+    }
+
     pub async fn solvers_sorted_by_mem(&self, ids: &[u64], system: &System) -> Vec<(u64, u64)> {
         let solvers: Vec<(u32, u64)> = {
             let map = self.solver_processes.lock().await;
@@ -768,8 +1416,40 @@ impl SolverManager {
         solver_mem
     }
 
-    pub async fn get_best_objective(&self) -> Option<ObjectiveValue> {
-        *self.best_objective.read().await
+    /// Waits until one of the given solvers has written a line of output
+    pub async fn wait_for_first_output(&self, ids: &[u64]) {
+        let mut first_outputs = self.first_outputs.subscribe();
+        let _ = first_outputs
+            .wait_for(|started| ids.iter().any(|id| started.contains(id)))
+            .await;
+    }
+
+    /// Waits until the solutions that were sent before the cancellation are printed, or until the timeout.
+    pub async fn wait_for_output(&self, timeout: Duration) {
+        let receiver = self.receiver.lock().await.take();
+        if let Some(receiver) = receiver
+            && tokio::time::timeout(timeout, receiver).await.is_err()
+        {
+            logging::warning!("timed out waiting for the last solutions to be printed");
+        }
+    }
+
+    pub fn printed_solution_count(&self) -> u64 {
+        self.printed_count.load(Ordering::Relaxed)
+    }
+
+    /// The time since the solver manager was created, which is right after the start of the program
+    pub fn uptime(&self) -> Duration {
+        self.started_at.elapsed()
+    }
+
+    pub fn get_best_objective(&self) -> Option<ObjectiveValue> {
+        *self.best_objective.borrow()
+    }
+
+    /// Returns a receiver that is notified every time a better objective is found.
+    pub fn subscribe_best_objective(&self) -> watch::Receiver<Option<ObjectiveValue>> {
+        self.best_objective.subscribe()
     }
 
     pub async fn get_solver_objectives(&self) -> HashMap<u64, Option<ObjectiveValue>> {
@@ -781,10 +1461,65 @@ impl SolverManager {
             .collect()
     }
 
+    pub fn solver_info(&self) -> &solver_config::Solvers {
+        &self.solver_info
+    }
+
     pub fn objective_type(&self) -> ObjectiveType {
         self.objective_type
     }
 
+    /// The metrics of this run, of both the stopped and the active solvers
+    pub async fn metrics(&self) -> Metrics {
+        let mut run_metrics = self.metrics_recorder.snapshot();
+        for process in self.solver_processes.lock().await.values() {
+            run_metrics
+                .entry(process.metrics_key.clone())
+                .or_default()
+                .merge(&process.metrics, self.objective_type);
+        }
+        run_metrics
+    }
+
+    /// Adds the metrics of this run to the metrics file of the problem instance
+    pub async fn dump_metrics(&self) {
+        let run_metrics = self.metrics().await;
+        if let Err(e) = self.save_metrics(&run_metrics).await {
+            logging::error!(e.into());
+        }
+    }
+
+    async fn save_metrics(&self, run_metrics: &Metrics) -> Result<()> {
+        let Some(path) = metrics::path(&self.args).await? else {
+            return Ok(());
+        };
+
+        let mut metrics = metrics::load(&path).await?;
+        for (metrics_key, solver_metrics) in run_metrics {
+            metrics
+                .entry(metrics_key.clone())
+                .or_default()
+                .merge(solver_metrics, self.objective_type);
+        }
+        metrics::save(&path, &metrics).await?;
+        logging::info!("Saved the solver metrics to '{}'", path.display());
+        Ok(())
+    }
+
+    /// Waits for the force kills of stopped solvers to finish, such that no solver processes are left behind.
+    pub async fn wait_for_pending_force_kills(&self) {
+        let handles = self.force_kills.take();
+        let result = tokio::task::spawn_blocking(move || {
+            for handle in handles {
+                let _ = handle.join();
+            }
+        })
+        .await;
+        if let Err(e) = result {
+            logging::error!(Error::from(e).into());
+        }
+    }
+
     async fn kill_solver(
         id: u64,
         solvers_map: &mut tokio::sync::MutexGuard<'_, HashMap<u64, SolverProcess>>,
@@ -798,9 +1533,45 @@ impl SolverManager {
     }
 }
 
-fn pipe(mut left: Command, mut right: Command) -> Result<PipeCommand> {
+fn get_available_cores() -> Result<BTreeSet<usize>> {
+    let cores: BTreeSet<usize> = core_affinity::get_core_ids()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|core| core.id)
+        .collect();
+    if !cores.is_empty() {
+        return Ok(cores);
+    }
+
+    // This can happen inside containers, e.g. Docker without `--cpuset-cpus`
+    let parallelism = std::thread::available_parallelism()
+        .map_err(|e| Error::CPUCoresRetrieval(format!("Could not retrieve system cores: {e}")))?;
+    logging::warning!(
+        "Could not retrieve the system core IDs, assuming cores 0 to {} are available",
+        parallelism.get() - 1
+    );
+    Ok((0..parallelism.get()).collect())
+}
+
+/// Pipes the stdout of the left command into the right command. If given, the stdin payload is written to the
+/// left command, whose stdin is closed afterwards.
+fn pipe(mut left: Command, mut right: Command, left_stdin: Option<Vec<u8>>) -> Result<PipeCommand> {
+    if left_stdin.is_some() {
+        left.stdin(Stdio::piped());
+    }
     let mut left_child = left.stdout(Stdio::piped()).spawn()?;
 
+    if let Some(payload) = left_stdin
+        && let Some(mut stdin) = left_child.stdin.take()
+    {
+        // Written in the background, such that a solver that reads its stdin late does not block the start
+        tokio::spawn(async move {
+            if let Err(e) = stdin.write_all(&payload).await {
+                logging::warning!("failed to write the hint to the solver: {e}");
+            }
+        });
+    }
+
     #[cfg(unix)]
     {
         let left_pid = left_child
@@ -830,6 +1601,13 @@ fn pipe(mut left: Command, mut right: Command) -> Result<PipeCommand> {
     })
 }
 
+/// Identifies a solution without keeping it in memory
+fn solution_hash(solution: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    solution.hash(&mut hasher);
+    hasher.finish()
+}
+
 #[cfg(target_os = "linux")]
 async fn pin_yuck_solver_to_cores(
     pid: u32,
@@ -880,3 +1658,157 @@ enum HandleStdoutError {
     #[error("failed to pipe from fzn to ozn")]
     Pipe(#[from] tokio::task::JoinError),
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::exit_code::ExitCode;
+
+    #[test]
+    fn test_schedule_args_are_added_to_the_extra_fzn_flags() {
+        let mut args = RunArgs::for_testing("model.mzn".into());
+        args.extra_fzn_flags = vec![("gecode".to_owned(), vec!["--restart".to_owned()])];
+        let config = Config::new(&args, &solver_config::Solvers::empty()).unwrap();
+
+        let cmd = SolverManager::get_solver_command(
+            Path::new("model.fzn"),
+            "gecode",
+            1,
+            &solver_config::Solvers::empty(),
+            Path::new("minizinc"),
+            &config.solver_args,
+            Some(&["--restart-base".to_owned(), "1.5".to_owned()]),
+            &[],
+        )
+        .unwrap();
+
+        let cmd_args: Vec<_> = cmd.as_std().get_args().collect();
+        assert_eq!(
+            cmd_args,
+            [
+                "--solver",
+                "gecode",
+                "model.fzn",
+                "--restart",
+                "--restart-base",
+                "1.5",
+                "-p",
+                "1"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_pipe_task_is_aborted_when_the_handler_stops() {
+        // A pipe that never finishes, since the writing end is kept open
+        let (_writer, mut reader) = tokio::io::duplex(64);
+        let pipe = tokio::spawn(async move {
+            let mut sink = tokio::io::sink();
+            tokio::io::copy(&mut reader, &mut sink).await
+        });
+
+        let guard = AbortOnDrop(pipe.abort_handle());
+        drop(guard);
+
+        let result = tokio::time::timeout(Duration::from_millis(100), pipe)
+            .await
+            .expect("the pipe task should have stopped");
+        assert!(result.unwrap_err().is_cancelled());
+    }
+
+    #[test]
+    fn test_budget_is_not_reset_by_restarts() {
+        let budget = Duration::from_secs(10);
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+        let mut usage = BudgetUsage::default();
+
+        assert_eq!(usage.start_remaining(budget, at(0)), budget);
+        usage.stop(at(4));
+        // Restarted with a better bound, the time between the processes is not counted
+        assert_eq!(usage.start_remaining(budget, at(6)), Duration::from_secs(6));
+        // An overlap restart, while the previous process is still running
+        assert_eq!(usage.start_remaining(budget, at(8)), Duration::from_secs(4));
+        usage.stop(at(9));
+        usage.stop(at(12));
+
+        assert_eq!(usage.start_remaining(budget, at(20)), Duration::ZERO);
+    }
+
+    /// Runs the receiver until all messages of the solvers are handled, returning whether the run was cancelled
+    /// and the exit code derived from the output
+    async fn receive(
+        objective_type: ObjectiveType,
+        warn_on_unsatisfiable: bool,
+        solver_ids: &[u64],
+        messages: Vec<Msg>,
+    ) -> (bool, ExitCode) {
+        let args = RunArgs::for_testing("model.mzn".into());
+        let solution_writer = SolutionWriter::new(&args).await.unwrap();
+        let token = CancellationToken::new();
+        let (tx, rx) = mpsc::unbounded_channel();
+        for message in messages {
+            tx.send(message).unwrap();
+        }
+        drop(tx);
+
+        SolverManager::receiver(
+            rx,
+            objective_type,
+            OutputMode::Dzn,
+            Arc::new(watch::Sender::new(None)),
+            warn_on_unsatisfiable,
+            None,
+            false,
+            false,
+            false,
+            None,
+            Arc::new(Mutex::new(solver_ids.iter().copied().collect())),
+            Default::default(),
+            solution_writer.clone(),
+            token.clone(),
+        )
+        .await;
+
+        (token.is_cancelled(), solution_writer.exit_code())
+    }
+
+    #[tokio::test]
+    async fn test_unsatisfiable_is_terminal_by_default() {
+        let messages = vec![Msg::Status(1, Status::Unsatisfiable)];
+        let result = receive(ObjectiveType::Satisfy, false, &[1, 2], messages).await;
+        assert_eq!(result, (true, ExitCode::Unsatisfiable));
+    }
+
+    #[tokio::test]
+    async fn test_warn_on_unsatisfiable_waits_for_all_solvers() {
+        let messages = vec![Msg::Status(1, Status::Unsatisfiable)];
+        let result = receive(ObjectiveType::Satisfy, true, &[1, 2], messages).await;
+        assert_eq!(result, (false, ExitCode::Unknown));
+
+        let messages = vec![
+            Msg::Status(1, Status::Unsatisfiable),
+            Msg::Status(1, Status::Unsatisfiable),
+            Msg::Status(2, Status::Unsatisfiable),
+        ];
+        let result = receive(ObjectiveType::Satisfy, true, &[1, 2], messages).await;
+        assert_eq!(result, (true, ExitCode::Unsatisfiable));
+    }
+
+    #[tokio::test]
+    async fn test_warn_on_unsatisfiable_keeps_solutions_of_other_solvers() {
+        let messages = vec![
+            Msg::Status(1, Status::Unsatisfiable),
+            Msg::Solution(
+                "gecode".to_owned(),
+                Solution {
+                    solution: "x = 1;\n".to_owned(),
+                    objective: None,
+                },
+            ),
+        ];
+        let result = receive(ObjectiveType::Satisfy, true, &[1, 2], messages).await;
+        assert_eq!(result, (true, ExitCode::Solution));
+    }
+}
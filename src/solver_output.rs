@@ -1,10 +1,23 @@
-use crate::model_parser::{ObjectiveType, ObjectiveValue};
+pub mod json_stream;
 
+use crate::args::OutputMode;
+use crate::model_parser::{ObjectiveDomain, ObjectiveType, ObjectiveValue};
+
+/// Parses the output of a solver line by line.
+/// The parser is stateful, so it must be reset (or recreated) between solver sessions.
 #[derive(Debug)]
 pub struct Parser {
     input: String,
     objective: Option<ObjectiveValue>,
     objective_type: ObjectiveType,
+    objective_domain: ObjectiveDomain,
+}
+
+/// Parses the solver output in the format of the output mode
+#[derive(Debug)]
+pub enum OutputParser {
+    Dzn(Parser),
+    JsonStream(json_stream::Parser),
 }
 
 #[derive(Debug)]
@@ -13,12 +26,18 @@ pub enum Output {
     Status(Status),
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
 pub enum Status {
+    #[error("optimal solution found")]
     OptimalSolution,
+    #[error("unsatisfiable")]
     Unsatisfiable,
+    #[error("unbounded")]
     Unbounded,
+    #[error("unknown")]
     Unknown,
+    #[error("all solutions found")]
+    AllSolutions,
 }
 
 pub const SOLUTION_TERMINATOR: &str = "----------";
@@ -26,6 +45,7 @@ pub const DONE_TERMINATOR: &str = "==========";
 pub const UNSATISFIABLE_TERMINATOR: &str = "=====UNSATISFIABLE=====";
 pub const UNBOUNDED_TERMINATOR: &str = "=====UNBOUNDED=====";
 pub const UNKNOWN_TERMINATOR: &str = "=====UNKNOWN=====";
+pub const ALL_SOLUTIONS_TERMINATOR: &str = "=====ALL SOLUTIONS=====";
 
 #[derive(Debug)]
 pub struct Solution {
@@ -33,6 +53,28 @@ pub struct Solution {
     pub objective: Option<ObjectiveValue>,
 }
 
+impl Solution {
+    /// The solution followed by the solution terminator, which is not part of `solution` itself
+    pub fn to_dzn_string(&self) -> String {
+        format!("{}{SOLUTION_TERMINATOR}", self.solution)
+    }
+
+    pub fn to_output_string(&self, output_mode: OutputMode) -> String {
+        match output_mode {
+            OutputMode::Dzn | OutputMode::Model => self.to_dzn_string(),
+            OutputMode::JsonStream => self.solution.clone(),
+        }
+    }
+}
+
+/// A comment line in the output, such as the solver that found a solution with `--tag-solutions`
+pub fn comment_output_string(comment: &str, output_mode: OutputMode) -> String {
+    match output_mode {
+        OutputMode::Dzn | OutputMode::Model => format!("% {comment}"),
+        OutputMode::JsonStream => json_stream::comment_string(comment),
+    }
+}
+
 impl Status {
     pub fn to_dzn_string(&self) -> &str {
         match self {
@@ -40,6 +82,39 @@ impl Status {
             Status::Unsatisfiable => UNSATISFIABLE_TERMINATOR,
             Status::Unbounded => UNBOUNDED_TERMINATOR,
             Status::Unknown => UNKNOWN_TERMINATOR,
+            Status::AllSolutions => ALL_SOLUTIONS_TERMINATOR,
+        }
+    }
+
+    pub fn to_output_string(&self, output_mode: OutputMode) -> String {
+        match output_mode {
+            OutputMode::Dzn | OutputMode::Model => self.to_dzn_string().to_owned(),
+            OutputMode::JsonStream => self.to_json_stream_string(),
+        }
+    }
+}
+
+impl OutputParser {
+    pub fn new(
+        output_mode: OutputMode,
+        objective_type: ObjectiveType,
+        objective_domain: ObjectiveDomain,
+    ) -> Self {
+        match output_mode {
+            // The output item is followed by the objective and the terminators like the DZN output
+            OutputMode::Dzn | OutputMode::Model => {
+                Self::Dzn(Parser::new(objective_type, objective_domain))
+            }
+            OutputMode::JsonStream => {
+                Self::JsonStream(json_stream::Parser::new(objective_type, objective_domain))
+            }
+        }
+    }
+
+    pub fn next_line(&mut self, line: &str) -> Result<Option<Output>> {
+        match self {
+            Self::Dzn(parser) => parser.next_line(line),
+            Self::JsonStream(parser) => parser.next_line(line),
         }
     }
 }
@@ -57,14 +132,21 @@ pub enum Error {
 pub type Result<T> = std::result::Result<T, Error>;
 
 impl Parser {
-    pub fn new(objective_type: ObjectiveType) -> Self {
+    pub fn new(objective_type: ObjectiveType, objective_domain: ObjectiveDomain) -> Self {
         Self {
             input: "".to_owned(),
             objective: None,
             objective_type,
+            objective_domain,
         }
     }
 
+    /// Clears the partially parsed solution
+    pub fn reset(&mut self) {
+        self.input.clear();
+        self.objective = None;
+    }
+
     fn take_solution(&mut self) -> Result<Solution> {
         let objective = match self.objective {
             None => {
@@ -77,9 +159,8 @@ impl Parser {
             val => val,
         };
 
-        // Clear state
-        self.objective = None;
         let input = std::mem::take(&mut self.input);
+        self.reset();
 
         Ok(Solution {
             solution: input,
@@ -87,34 +168,39 @@ impl Parser {
         })
     }
 
+    fn parse_status(line: &str) -> Option<Status> {
+        match line {
+            DONE_TERMINATOR => Some(Status::OptimalSolution),
+            UNSATISFIABLE_TERMINATOR => Some(Status::Unsatisfiable),
+            UNBOUNDED_TERMINATOR => Some(Status::Unbounded),
+            UNKNOWN_TERMINATOR => Some(Status::Unknown),
+            ALL_SOLUTIONS_TERMINATOR => Some(Status::AllSolutions),
+            _ => None,
+        }
+    }
+
     pub fn next_line(&mut self, line: &str) -> Result<Option<Output>> {
         const OBJECTIVE_PREFIX: &str = "_objective = ";
 
         let line = line.trim();
 
+        if line == SOLUTION_TERMINATOR {
+            return Ok(Some(Output::Solution(self.take_solution()?)));
+        } else if let Some(status) = Self::parse_status(line) {
+            self.reset();
+            return Ok(Some(Output::Status(status)));
+        }
+
         self.input += line;
         self.input += "\n";
 
-        if line == SOLUTION_TERMINATOR {
-            Ok(Some(Output::Solution(self.take_solution()?)))
-        } else if line == DONE_TERMINATOR {
-            Ok(Some(Output::Status(Status::OptimalSolution)))
-        } else if line == UNSATISFIABLE_TERMINATOR {
-            Ok(Some(Output::Status(Status::Unsatisfiable)))
-        } else if line == UNBOUNDED_TERMINATOR {
-            Ok(Some(Output::Status(Status::Unbounded)))
-        } else if line == UNKNOWN_TERMINATOR {
-            Ok(Some(Output::Status(Status::Unknown)))
-        } else if self.objective_type != ObjectiveType::Satisfy
-            && line.starts_with(OBJECTIVE_PREFIX)
-        {
+        if self.objective_type != ObjectiveType::Satisfy && line.starts_with(OBJECTIVE_PREFIX) {
             let objective_str: String = line[OBJECTIVE_PREFIX.len()..]
                 .chars()
                 .take_while(|c| *c != ';')
                 .collect();
-            let objective = objective_str
-                .parse::<ObjectiveValue>()
-                .map_err(|_| Error::ObjectiveParse)?;
+            let objective = ObjectiveValue::parse(&objective_str, self.objective_domain)
+                .ok_or(Error::ObjectiveParse)?;
             self.objective = Some(objective);
             Ok(None)
         } else {
@@ -122,3 +208,92 @@ impl Parser {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_lines(parser: &mut Parser, lines: &[&str]) -> Vec<Output> {
+        lines
+            .iter()
+            .filter_map(|line| parser.next_line(line).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_solution_does_not_include_the_terminator() {
+        let mut parser = Parser::new(ObjectiveType::Satisfy, ObjectiveDomain::Int);
+        let outputs = parse_lines(&mut parser, &["x = 1;", SOLUTION_TERMINATOR]);
+
+        let [Output::Solution(solution)] = outputs.as_slice() else {
+            panic!("expected a single solution, got {outputs:?}");
+        };
+        assert_eq!(solution.solution, "x = 1;\n");
+        assert_eq!(solution.objective, None);
+        assert_eq!(
+            solution.to_dzn_string(),
+            format!("x = 1;\n{SOLUTION_TERMINATOR}")
+        );
+    }
+
+    #[test]
+    fn test_solution_objective_is_parsed() {
+        let mut parser = Parser::new(ObjectiveType::Minimize, ObjectiveDomain::Int);
+        let outputs = parse_lines(
+            &mut parser,
+            &["x = 1;", "_objective = 42;", SOLUTION_TERMINATOR],
+        );
+
+        let [Output::Solution(solution)] = outputs.as_slice() else {
+            panic!("expected a single solution, got {outputs:?}");
+        };
+        assert_eq!(solution.solution, "x = 1;\n_objective = 42;\n");
+        assert_eq!(solution.objective, Some(ObjectiveValue::Int(42)));
+    }
+
+    #[test]
+    fn test_consecutive_solutions_are_separate() {
+        let mut parser = Parser::new(ObjectiveType::Satisfy, ObjectiveDomain::Int);
+        let outputs = parse_lines(
+            &mut parser,
+            &["x = 1;", SOLUTION_TERMINATOR, "x = 2;", SOLUTION_TERMINATOR],
+        );
+
+        let solutions: Vec<&str> = outputs
+            .iter()
+            .map(|output| match output {
+                Output::Solution(solution) => solution.solution.as_str(),
+                Output::Status(status) => panic!("unexpected status {status:?}"),
+            })
+            .collect();
+        assert_eq!(solutions, ["x = 1;\n", "x = 2;\n"]);
+    }
+
+    #[test]
+    fn test_status_terminators() {
+        for status in [
+            Status::OptimalSolution,
+            Status::Unsatisfiable,
+            Status::Unbounded,
+            Status::Unknown,
+            Status::AllSolutions,
+        ] {
+            let mut parser = Parser::new(ObjectiveType::Satisfy, ObjectiveDomain::Int);
+            let outputs = parse_lines(&mut parser, &[status.to_dzn_string()]);
+            assert!(
+                matches!(outputs.as_slice(), [Output::Status(parsed)] if *parsed == status),
+                "{status:?} parsed as {outputs:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_missing_objective_is_an_error() {
+        let mut parser = Parser::new(ObjectiveType::Maximize, ObjectiveDomain::Int);
+        assert!(parser.next_line("x = 1;").unwrap().is_none());
+        assert!(matches!(
+            parser.next_line(SOLUTION_TERMINATOR),
+            Err(Error::SolutionMissingObjective)
+        ));
+    }
+}
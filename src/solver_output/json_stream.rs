@@ -0,0 +1,144 @@
+use super::{Error, Output, Result, Solution, Status};
+use crate::model_parser::{ObjectiveDomain, ObjectiveType, ObjectiveValue};
+
+/// Parses the output of a solver run with `--json-stream`, where every line is a JSON object with a `type` field.
+#[derive(Debug)]
+pub struct Parser {
+    objective_type: ObjectiveType,
+    objective_domain: ObjectiveDomain,
+}
+
+impl Parser {
+    pub fn new(objective_type: ObjectiveType, objective_domain: ObjectiveDomain) -> Self {
+        Self {
+            objective_type,
+            objective_domain,
+        }
+    }
+
+    pub fn next_line(&mut self, line: &str) -> Result<Option<Output>> {
+        let line = line.trim();
+        if line.is_empty() {
+            return Ok(None);
+        }
+
+        let message: serde_json::Value = serde_json::from_str(line)?;
+        match message["type"].as_str() {
+            Some("solution") => {
+                let objective = self.parse_objective(&message)?;
+                Ok(Some(Output::Solution(Solution {
+                    solution: line.to_owned(),
+                    objective,
+                })))
+            }
+            Some("status") => Ok(Some(Output::Status(parse_status(&message)))),
+            // Comments, timestamps, errors, etc. are not needed for scheduling
+            _ => Ok(None),
+        }
+    }
+
+    fn parse_objective(&self, message: &serde_json::Value) -> Result<Option<ObjectiveValue>> {
+        if self.objective_type == ObjectiveType::Satisfy {
+            return Ok(None);
+        }
+
+        let objective = &message["output"]["json"]["_objective"];
+        if objective.is_null() {
+            return Err(Error::SolutionMissingObjective);
+        }
+        ObjectiveValue::from_json(objective, self.objective_domain)
+            .map(Some)
+            .ok_or(Error::ObjectiveParse)
+    }
+}
+
+fn parse_status(message: &serde_json::Value) -> Status {
+    match message["status"].as_str() {
+        Some(OPTIMAL_SOLUTION) => Status::OptimalSolution,
+        Some(UNSATISFIABLE) => Status::Unsatisfiable,
+        Some(UNBOUNDED) => Status::Unbounded,
+        Some(ALL_SOLUTIONS) => Status::AllSolutions,
+        _ => Status::Unknown,
+    }
+}
+
+const OPTIMAL_SOLUTION: &str = "OPTIMAL_SOLUTION";
+const UNSATISFIABLE: &str = "UNSATISFIABLE";
+const UNBOUNDED: &str = "UNBOUNDED";
+const UNKNOWN: &str = "UNKNOWN";
+const ALL_SOLUTIONS: &str = "ALL_SOLUTIONS";
+
+/// A comment message, which MiniZinc prints for the comments in the solver output
+pub fn comment_string(comment: &str) -> String {
+    serde_json::json!({ "type": "comment", "comment": format!("% {comment}\n") }).to_string()
+}
+
+impl Status {
+    pub fn to_json_stream_string(&self) -> String {
+        let status = match self {
+            Status::OptimalSolution => OPTIMAL_SOLUTION,
+            Status::Unsatisfiable => UNSATISFIABLE,
+            Status::Unbounded => UNBOUNDED,
+            Status::Unknown => UNKNOWN,
+            Status::AllSolutions => ALL_SOLUTIONS,
+        };
+        serde_json::json!({ "type": "status", "status": status }).to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_solution_objective_is_parsed() {
+        let mut parser = Parser::new(ObjectiveType::Minimize, ObjectiveDomain::Int);
+        let line = r#"{"type": "solution", "output": {"json": {"x": 1, "_objective": 42}}}"#;
+
+        let Some(Output::Solution(solution)) = parser.next_line(line).unwrap() else {
+            panic!("expected a solution");
+        };
+        assert_eq!(solution.solution, line);
+        assert_eq!(solution.objective, Some(ObjectiveValue::Int(42)));
+    }
+
+    #[test]
+    fn test_missing_objective_is_an_error() {
+        let mut parser = Parser::new(ObjectiveType::Maximize, ObjectiveDomain::Int);
+        let line = r#"{"type": "solution", "output": {"json": {"x": 1}}}"#;
+        assert!(matches!(
+            parser.next_line(line),
+            Err(Error::SolutionMissingObjective)
+        ));
+    }
+
+    #[test]
+    fn test_status_round_trips() {
+        for status in [
+            Status::OptimalSolution,
+            Status::Unsatisfiable,
+            Status::Unbounded,
+            Status::Unknown,
+            Status::AllSolutions,
+        ] {
+            let mut parser = Parser::new(ObjectiveType::Satisfy, ObjectiveDomain::Int);
+            let parsed = parser.next_line(&status.to_json_stream_string()).unwrap();
+            assert!(
+                matches!(&parsed, Some(Output::Status(parsed)) if *parsed == status),
+                "{status:?} parsed as {parsed:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_other_messages_are_ignored() {
+        let mut parser = Parser::new(ObjectiveType::Satisfy, ObjectiveDomain::Int);
+        assert!(parser.next_line("").unwrap().is_none());
+        assert!(
+            parser
+                .next_line(&comment_string("a comment"))
+                .unwrap()
+                .is_none()
+        );
+    }
+}
@@ -1,15 +1,24 @@
 use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use itertools::Itertools;
+use serde::Deserialize;
 
 use crate::{
     args::{RunArgs, Verbosity},
     logging,
+    portfolio_file::{self, PortfolioFile},
     scheduler::{Portfolio, SolverInfo},
-    solvers,
+    solver_config, solvers,
 };
 
-pub async fn static_schedule(args: &RunArgs, cores: usize) -> Result<Portfolio> {
+pub async fn static_schedule(
+    args: &RunArgs,
+    cores: usize,
+    solvers: &solver_config::Solvers,
+) -> Result<Portfolio> {
     let schedule = match args.static_schedule.as_ref() {
-        Some(path) => get_schedule_from_file(path).await?,
+        Some(path) => get_schedule_from_file(path, solvers, args.strict_solver_validation).await?,
         None => default_schedule(cores),
     };
 
@@ -25,9 +34,13 @@ pub async fn static_schedule(args: &RunArgs, cores: usize) -> Result<Portfolio>
     Ok(schedule)
 }
 
-pub async fn timeout_schedule(args: &RunArgs, cores: usize) -> Result<Portfolio> {
+pub async fn timeout_schedule(
+    args: &RunArgs,
+    cores: usize,
+    solvers: &solver_config::Solvers,
+) -> Result<Portfolio> {
     let schedule = match args.timeout_schedule.as_ref() {
-        Some(path) => get_schedule_from_file(path).await?,
+        Some(path) => get_schedule_from_file(path, solvers, args.strict_solver_validation).await?,
         None => default_schedule(cores),
     };
 
@@ -47,14 +60,56 @@ fn schedule_cores(schedule: &Portfolio) -> usize {
     schedule.iter().map(|solver_info| solver_info.cores).sum()
 }
 
-async fn get_schedule_from_file(path: &Path) -> Result<Portfolio> {
+/// Reads a schedule file. The format is detected by the extension: `.toml` is a TOML schedule, `.json` is a JSON
+/// schedule if it is an object and a JSON portfolio file if it is an array, and anything else is a CSV schedule.
+async fn get_schedule_from_file(
+    path: &Path,
+    solvers: &solver_config::Solvers,
+    strict_solver_validation: bool,
+) -> Result<Portfolio> {
     let contents = tokio::fs::read_to_string(path)
         .await
         .map_err(|e| Error::FileError {
             path: path.to_path_buf(),
             source: e,
         })?;
-    parse_schedule(&contents).map_err(Into::into)
+
+    let portfolio = match path.extension().and_then(|extension| extension.to_str()) {
+        Some("toml") => parse_schedule_toml(&contents)?,
+        Some("json") if contents.trim_start().starts_with('[') => {
+            PortfolioFile::parse(&contents)?.into_portfolio(solvers)
+        }
+        Some("json") => parse_schedule_json(&contents)?,
+        _ => parse_schedule(&contents)?,
+    };
+
+    let unknown_solvers = validate_portfolio(&portfolio, solvers);
+    if strict_solver_validation && !unknown_solvers.is_empty() {
+        return Err(Error::UnknownSolvers(unknown_solvers));
+    }
+    Ok(portfolio)
+}
+
+/// Warns about the solvers in the portfolio that were not discovered, and returns their names.
+/// Nothing is reported if no solvers were discovered at all, since the discovery failure is already logged.
+fn validate_portfolio(portfolio: &Portfolio, solvers: &solver_config::Solvers) -> Vec<String> {
+    if solvers.is_empty() {
+        return Vec::new();
+    }
+
+    let unknown_solvers: Vec<String> = portfolio
+        .iter()
+        .filter(|solver_info| solvers.get_by_id(&solver_info.name).is_none())
+        .map(|solver_info| solver_info.name.clone())
+        .unique()
+        .collect();
+
+    for name in &unknown_solvers {
+        logging::warning!(
+            "The schedule contains the solver '{name}', which is not among the installed solvers"
+        );
+    }
+    unknown_solvers
 }
 
 pub fn parse_schedule(s: &str) -> std::result::Result<Portfolio, ParseError> {
@@ -64,12 +119,58 @@ pub fn parse_schedule(s: &str) -> std::result::Result<Portfolio, ParseError> {
         .collect()
 }
 
+/// A schedule where each solver is a table, e.g. `[[solvers]] name = "gecode" cores = 2 args = ["-i"]` in TOML
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ScheduleFile {
+    solvers: Vec<ScheduleFileSolver>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ScheduleFileSolver {
+    name: String,
+    cores: usize,
+    /// Passed after the arguments for the solver from the config
+    args: Option<Vec<String>>,
+    /// Written to the stdin of the solver when it starts
+    hint_file: Option<PathBuf>,
+    /// In seconds, after which the solver is stopped and not started again
+    time_budget: Option<u64>,
+}
+
+impl ScheduleFile {
+    fn into_portfolio(self) -> Portfolio {
+        self.solvers
+            .into_iter()
+            .map(|solver| {
+                let mut info = SolverInfo::new(solver.name, solver.cores);
+                info.extra_args = solver.args;
+                info.hint_file = solver.hint_file;
+                info.time_budget = solver.time_budget.map(Duration::from_secs);
+                info
+            })
+            .collect()
+    }
+}
+
+pub fn parse_schedule_toml(s: &str) -> std::result::Result<Portfolio, ParseError> {
+    Ok(toml::from_str::<ScheduleFile>(s)?.into_portfolio())
+}
+
+pub fn parse_schedule_json(s: &str) -> std::result::Result<Portfolio, ParseError> {
+    Ok(serde_json::from_str::<ScheduleFile>(s)?.into_portfolio())
+}
+
+/// Parses a line in the format of `<solver>,<cores>` or `<solver>,<cores>,<time budget in seconds>`
 fn parse_schedule_line(line: &str) -> std::result::Result<SolverInfo, ParseError> {
-    let (solver, cores_str) =
-        line.split_once(',')
-            .ok_or_else(|| ParseError::LineDoesNotContainComma {
-                line: line.to_owned(),
-            })?;
+    let mut fields = line.splitn(3, ',');
+    let solver = fields.next().unwrap_or_default();
+    let cores_str = fields
+        .next()
+        .ok_or_else(|| ParseError::LineDoesNotContainComma {
+            line: line.to_owned(),
+        })?;
 
     let cores = cores_str
         .parse::<usize>()
@@ -78,15 +179,32 @@ fn parse_schedule_line(line: &str) -> std::result::Result<SolverInfo, ParseError
             cores_str: cores_str.to_owned(),
         })?;
 
-    Ok(SolverInfo::new(solver.to_owned(), cores))
+    let time_budget = fields
+        .next()
+        .map(|budget_str| {
+            budget_str
+                .parse::<u64>()
+                .map(Duration::from_secs)
+                .map_err(|_| ParseError::TimeBudgetNotANumber {
+                    line: line.to_owned(),
+                    budget_str: budget_str.to_owned(),
+                })
+        })
+        .transpose()?;
+
+    let mut info = SolverInfo::new(solver.to_owned(), cores);
+    info.time_budget = time_budget;
+    Ok(info)
 }
 
+/// Gives all the cores to a single solver, which should at least get one core
 fn default_schedule(cores: usize) -> Portfolio {
-    vec![SolverInfo::new(solvers::CP_SAT_ID.to_owned(), cores)]
+    vec![SolverInfo::new(solvers::CP_SAT_ID.to_owned(), cores.max(1))]
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
 
+#[allow(clippy::enum_variant_names)]
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
     #[error("IO failed when reading file '{path}'")]
@@ -97,6 +215,10 @@ pub enum Error {
     },
     #[error("Parsing of the static schedule failed")]
     ParseError(#[from] ParseError),
+    #[error("Parsing of the portfolio file failed")]
+    PortfolioFile(#[from] portfolio_file::Error),
+    #[error("The schedule contains solvers that are not installed: {}", .0.join(", "))]
+    UnknownSolvers(Vec<String>),
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -107,4 +229,77 @@ pub enum ParseError {
         "A solver's cores in the schedule is not an unsigned integer: '{cores_str}' on the following line: {line}"
     )]
     CoresNotANumber { line: String, cores_str: String },
+    #[error(
+        "A solver's time budget in the schedule is not an unsigned integer: '{budget_str}' on the following line: {line}"
+    )]
+    TimeBudgetNotANumber { line: String, budget_str: String },
+    #[error("The schedule is not valid TOML of the expected format")]
+    Toml(#[from] toml::de::Error),
+    #[error("The schedule is not valid JSON of the expected format")]
+    Json(#[from] serde_json::Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_time_budget() {
+        let portfolio = parse_schedule("gecode,2,30\ncoinbc,1\n").unwrap();
+        assert_eq!(portfolio[0].time_budget, Some(Duration::from_secs(30)));
+        assert_eq!(portfolio[1].time_budget, None);
+    }
+
+    #[test]
+    fn test_toml_time_budget() {
+        let portfolio = parse_schedule_toml(
+            "[[solvers]]\nname = \"gecode\"\ncores = 2\ntime_budget = 30\n\n[[solvers]]\nname = \"coinbc\"\ncores = 1\n",
+        )
+        .unwrap();
+        assert_eq!(portfolio[0].time_budget, Some(Duration::from_secs(30)));
+        assert_eq!(portfolio[1].time_budget, None);
+    }
+
+    #[test]
+    fn test_json_time_budget() {
+        let portfolio = parse_schedule_json(
+            r#"{"solvers": [{"name": "gecode", "cores": 2, "time_budget": 30}]}"#,
+        )
+        .unwrap();
+        assert_eq!(portfolio[0].time_budget, Some(Duration::from_secs(30)));
+    }
+
+    #[test]
+    fn test_portfolio_file_time_budget() {
+        let portfolio =
+            PortfolioFile::parse(r#"[{"id": "gecode", "cores": 2, "time_budget": 30}]"#)
+                .unwrap()
+                .into_portfolio(&solver_config::Solvers::empty());
+        assert_eq!(portfolio[0].time_budget, Some(Duration::from_secs(30)));
+    }
+
+    #[tokio::test]
+    async fn test_static_schedule_defaults_to_cp_sat_on_all_cores() {
+        let args = RunArgs::for_testing("model.mzn".into());
+        let schedule = static_schedule(&args, 4, &solver_config::Solvers::empty())
+            .await
+            .unwrap();
+        assert_eq!(
+            schedule,
+            [SolverInfo::new(solvers::CP_SAT_ID.to_owned(), 4)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_static_schedule_format_is_detected_by_extension() {
+        let file = tempfile::Builder::new().suffix(".toml").tempfile().unwrap();
+        std::fs::write(file.path(), "[[solvers]]\nname = \"gecode\"\ncores = 2\n").unwrap();
+        let mut args = RunArgs::for_testing("model.mzn".into());
+        args.static_schedule = Some(file.path().to_path_buf());
+
+        let schedule = static_schedule(&args, 2, &solver_config::Solvers::empty())
+            .await
+            .unwrap();
+        assert_eq!(schedule, [SolverInfo::new("gecode".to_owned(), 2)]);
+    }
 }
@@ -0,0 +1,73 @@
+use std::path::{Path, PathBuf};
+
+use directories::BaseDirs;
+use sha2::{Digest, Sha256};
+
+/// The directory `parasol/<sub>` in the cache directory of the platform, e.g. `~/.cache/parasol/<sub>` on Linux
+pub fn cache_dir(sub: &str) -> Option<PathBuf> {
+    let base_dirs = BaseDirs::new()?;
+    Some(base_dirs.cache_dir().join("parasol").join(sub))
+}
+
+/// The directory `parasol/<sub>` in the data directory of the platform, e.g. `~/.local/share/parasol/<sub>` on
+/// Linux
+pub fn data_dir(sub: &str) -> Option<PathBuf> {
+    let base_dirs = BaseDirs::new()?;
+    Some(base_dirs.data_dir().join("parasol").join(sub))
+}
+
+/// Hashes the part prefixed with its length, such that moving the boundary between two parts changes the hash
+pub fn update_part(hasher: &mut Sha256, part: &[u8]) {
+    hasher.update((part.len() as u64).to_le_bytes());
+    hasher.update(part);
+}
+
+/// The finished hash as lowercase hex
+pub fn sha256_hex(hasher: Sha256) -> String {
+    hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// Writes the file under a temporary name and renames it into place, such that a concurrent run never reads a
+/// partially written file
+pub async fn write_atomic(path: &Path, content: &[u8]) -> std::io::Result<()> {
+    let partial = partial_path(path);
+    tokio::fs::write(&partial, content).await?;
+    tokio::fs::rename(&partial, path).await
+}
+
+/// Like `write_atomic`, with the content of the source file
+pub async fn copy_atomic(source: &Path, path: &Path) -> std::io::Result<()> {
+    let partial = partial_path(path);
+    tokio::fs::copy(source, &partial).await?;
+    tokio::fs::rename(&partial, path).await
+}
+
+fn partial_path(path: &Path) -> PathBuf {
+    let mut partial = path.as_os_str().to_owned();
+    partial.push(format!(".{}.partial", std::process::id()));
+    partial.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parts_hash(parts: &[&str]) -> String {
+        let mut hasher = Sha256::new();
+        for part in parts {
+            update_part(&mut hasher, part.as_bytes());
+        }
+        sha256_hex(hasher)
+    }
+
+    #[test]
+    fn test_part_boundaries_change_the_hash() {
+        assert_eq!(parts_hash(&["ab", "c"]), parts_hash(&["ab", "c"]));
+        assert_ne!(parts_hash(&["ab", "c"]), parts_hash(&["a", "bc"]));
+        assert_ne!(parts_hash(&["abc"]), parts_hash(&["abc", ""]));
+    }
+}
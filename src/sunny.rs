@@ -1,14 +1,21 @@
+use futures::future::join_all;
 use std::sync::Arc;
 
 use crate::config::Config;
 use crate::fzn_to_features::{self, fzn_to_features};
+use crate::is_cancelled::IsCancelled;
+use crate::model_parser::ObjectiveValue;
 use crate::mzn_to_fzn;
-use crate::mzn_to_fzn::compilation_manager::CompilationManager;
+use crate::mzn_to_fzn::Conversion;
+use crate::mzn_to_fzn::compilation_manager::{CompilationManager, WaitForError};
 use crate::scheduler::{Portfolio, Scheduler};
 use crate::signal_handler::SignalEvent;
+use crate::solution_writer::SolutionWriter;
+use crate::solver_manager::SolverManager;
 use crate::static_schedule::{self, static_schedule, timeout_schedule};
-use crate::{ai, logging, solver_config, solver_manager};
+use crate::{ai, logging, solver_config, solver_manager, solvers};
 use crate::{ai::Ai, args::RunArgs};
+use tokio::sync::watch;
 use tokio::time::{Duration, sleep, timeout};
 use tokio_util::sync::CancellationToken;
 
@@ -32,23 +39,52 @@ pub enum Error {
     SolverManager(#[from] solver_manager::Error),
     #[error("All solvers failed, could not continue")]
     SolverFailure,
+    #[error("no solution was found within the time limit")]
+    TimeLimitWithoutSolution,
+    #[error("the solver filter removed all solvers from the schedule")]
+    EmptyFilteredPortfolio,
+    #[error("no static schedule file was given, so there is no schedule to reload")]
+    NoScheduleToReload,
 }
 
+/// How long to wait for the pending solutions to be printed after the cancellation
+const OUTPUT_FLUSH_TIMEOUT: Duration = Duration::from_millis(500);
+
+#[allow(clippy::too_many_arguments)]
 pub async fn sunny<T: Ai + Send + 'static>(
     args: &RunArgs,
     ai: Option<T>,
+    ab_test_ai: Option<T>,
     config: Config,
     solvers: Arc<solver_config::Solvers>,
+    solution_writer: SolutionWriter,
     program_cancellation_token: CancellationToken,
     suspend_and_resume_signal_rx: tokio::sync::mpsc::UnboundedReceiver<SignalEvent>,
 ) -> Result<(), Error> {
-    let compilation_manager = Arc::new(CompilationManager::new(Arc::new(args.clone())));
+    let compilation_manager = Arc::new(CompilationManager::new(
+        Arc::new(args.clone()),
+        solvers.clone(),
+        config.max_compilation_retries,
+    ));
+
+    if args.dry_run {
+        let (_, initial_solver_cores) = get_cores(args, &ai);
+        return dry_run(
+            args,
+            initial_solver_cores,
+            &solvers,
+            &compilation_manager,
+            &program_cancellation_token,
+        )
+        .await;
+    }
 
     let mut scheduler = Scheduler::new(
         args,
         &config,
         solvers,
         compilation_manager.clone(),
+        solution_writer,
         program_cancellation_token.clone(),
         suspend_and_resume_signal_rx,
     )
@@ -57,17 +93,51 @@ pub async fn sunny<T: Ai + Send + 'static>(
     let (cores, initial_solver_cores) = get_cores(args, &ai);
     // let solver_priority_order = get_priority_schedule()
 
-    let initial_schedule = static_schedule(args, initial_solver_cores).await?;
+    let initial_schedule = static_schedule(
+        args,
+        initial_solver_cores,
+        scheduler.solver_manager.solver_info(),
+    )
+    .await?;
+    let initial_schedule = filter_portfolio(initial_schedule, &args.solver_filter)?;
+
+    // Compile for all solvers of the initial schedule in parallel, before anything else waits on a compilation
+    compilation_manager
+        .start_many(initial_schedule.iter().map(|info| info.name.clone()))
+        .await;
+    if args.precompile_all {
+        precompile_all(
+            args,
+            scheduler.solver_manager.solver_info(),
+            &compilation_manager,
+        )
+        .await;
+    }
+
+    let time_limit_token = CancellationToken::new();
+    if let Some(time_limit) = args.time_limit {
+        spawn_time_limit(
+            Duration::from_secs(time_limit),
+            &initial_schedule,
+            compilation_manager.clone(),
+            time_limit_token.clone(),
+            program_cancellation_token.clone(),
+        );
+    }
 
     let static_runtime = Duration::from_secs(args.static_runtime);
-    let mut timer = sleep(static_runtime);
+    // Pinned, such that the restart is not postponed when the loop continues without applying a schedule
+    let timer = sleep(static_runtime);
+    tokio::pin!(timer);
 
     let start_cancellation_token = program_cancellation_token.child_token();
     let schedule = if let Some(ai) = ai {
         start_with_ai(
             args,
             ai,
+            ab_test_ai,
             &mut scheduler,
+            &config,
             initial_schedule,
             cores,
             start_cancellation_token,
@@ -75,46 +145,260 @@ pub async fn sunny<T: Ai + Send + 'static>(
         )
         .await
     } else {
-        start_without_ai(args, &mut scheduler, initial_schedule).await
-    }?;
+        start_without_ai(
+            args,
+            &mut scheduler,
+            initial_schedule,
+            start_cancellation_token,
+        )
+        .await
+    };
+    let schedule = match schedule {
+        Err(Error::Cancelled) => {
+            scheduler
+                .solver_manager
+                .wait_for_output(OUTPUT_FLUSH_TIMEOUT)
+                .await;
+            scheduler.solver_manager.dump_metrics().await;
+            return Err(cancellation_error(
+                &time_limit_token,
+                &scheduler.solver_manager,
+            ));
+        }
+        schedule => filter_portfolio(schedule?, &args.solver_filter)?,
+    };
+    let mut schedule = schedule;
 
     let restart_interval = Duration::from_secs(args.restart_interval);
+    let mut objective_rx = scheduler.solver_manager.subscribe_best_objective();
     // Restart loop, where it share bounds. It runs forever until it finds a solution, where it will then be cancelled by the cancellation token.
     loop {
         tokio::select! {
-            _ = timer => {}
-            _ = program_cancellation_token.cancelled() => {
-                return Err(Error::Cancelled)
+            _ = &mut timer => {}
+            () = objective_improved(&mut objective_rx, args.rerun_on_improvement) => {
+                logging::info!("found a better objective, restarting the solvers");
             }
+            () = scheduler.reload_requested() => {
+                match reload_static_schedule(args, cores, &scheduler).await {
+                    Ok(new_schedule) => {
+                        logging::info!("reloaded the static schedule: {new_schedule:?}");
+                        schedule = new_schedule;
+                    }
+                    Err(e) => {
+                        logging::error!(e.into());
+                        logging::warning!("keeping the current schedule");
+                        continue;
+                    }
+                }
+            }
+            _ = program_cancellation_token.cancelled() => break,
         }
 
-        let schedule_len = schedule.len();
-
         let apply_cancellation_token = scheduler.create_apply_token();
         if let Err(errors) = scheduler
-            .apply(schedule.clone(), apply_cancellation_token, true)
+            .apply(
+                schedule.clone(),
+                apply_cancellation_token,
+                !args.precompile_all,
+            )
             .await
         {
-            let errorlen = errors.len();
-            handle_schedule_errors(errors);
-            if errorlen == schedule_len {
-                return Err(Error::SolverFailure);
+            handle_apply_errors(errors, &scheduler.solver_manager).await?;
+        }
+
+        timer
+            .as_mut()
+            .reset(tokio::time::Instant::now() + restart_interval);
+    }
+
+    if let Err(errors) = scheduler.solver_manager.stop_all_solvers().await {
+        handle_schedule_errors(errors);
+    }
+    scheduler
+        .solver_manager
+        .wait_for_pending_force_kills()
+        .await;
+    scheduler
+        .solver_manager
+        .wait_for_output(OUTPUT_FLUSH_TIMEOUT)
+        .await;
+    scheduler.solver_manager.dump_metrics().await;
+    Err(cancellation_error(
+        &time_limit_token,
+        &scheduler.solver_manager,
+    ))
+}
+
+/// Resolves and compiles the initial schedule, and prints the solvers that would be started with it
+async fn dry_run(
+    args: &RunArgs,
+    cores: usize,
+    solvers: &solver_config::Solvers,
+    compilation_manager: &CompilationManager,
+    cancellation_token: &CancellationToken,
+) -> Result<(), Error> {
+    let schedule = static_schedule(args, cores, solvers).await?;
+    let schedule = filter_portfolio(schedule, &args.solver_filter)?;
+
+    compilation_manager
+        .start_many(schedule.iter().map(|info| info.name.clone()))
+        .await;
+
+    println!("Dry run, the following solvers would be started:");
+    for info in &schedule {
+        let Some(conversion) = cancellation_token
+            .run_until_cancelled(compilation_manager.wait_for(&info.name))
+            .await
+        else {
+            return Err(Error::Cancelled);
+        };
+        let compilation = match conversion {
+            Ok(conversion) => match (
+                conversion.fzn_size_bytes().await,
+                conversion.ozn_size_bytes().await,
+            ) {
+                (Ok(fzn_size), Ok(ozn_size)) => {
+                    format!("FlatZinc of {fzn_size} bytes, ozn of {ozn_size} bytes")
+                }
+                (Err(e), _) | (_, Err(e)) => format!("compilation size unknown: {e}"),
+            },
+            Err(e) => format!("compilation failed: {e}"),
+        };
+        let unit = if info.cores == 1 { "core" } else { "cores" };
+        println!("  {}: {} {unit}, {compilation}", info.name, info.cores);
+    }
+    Ok(())
+}
+
+/// Compiles for every discovered solver in the background, such that a later schedule can switch to any solver
+/// without waiting for its compilation. The compilations are kept when the schedule changes.
+async fn precompile_all(
+    args: &RunArgs,
+    solvers: &solver_config::Solvers,
+    compilation_manager: &CompilationManager,
+) {
+    let solver_ids: Vec<String> = solvers
+        .iter()
+        .map(|solver| solver.id())
+        .filter(|id| *id != solvers::SELF_ID)
+        .filter(|id| args.solver_filter.is_empty() || args.solver_filter.iter().any(|f| f == id))
+        .map(str::to_owned)
+        .collect();
+    logging::info!("precompiling for all {} solvers", solver_ids.len());
+    compilation_manager.start_many(solver_ids.into_iter()).await;
+}
+
+/// Reads the static schedule file again, which is requested with `SIGUSR2`
+async fn reload_static_schedule(
+    args: &RunArgs,
+    cores: usize,
+    scheduler: &Scheduler,
+) -> Result<Portfolio, Error> {
+    if args.static_schedule.is_none() {
+        return Err(Error::NoScheduleToReload);
+    }
+    let schedule = static_schedule(args, cores, scheduler.solver_manager.solver_info()).await?;
+    filter_portfolio(schedule, &args.solver_filter)
+}
+
+/// Removes the solvers that are not in the filter. An empty filter keeps all solvers.
+fn filter_portfolio(portfolio: Portfolio, filter: &[String]) -> Result<Portfolio, Error> {
+    if filter.is_empty() {
+        return Ok(portfolio);
+    }
+
+    let portfolio: Portfolio = portfolio
+        .into_iter()
+        .filter(|info| filter.contains(&info.name))
+        .collect();
+    if portfolio.is_empty() {
+        return Err(Error::EmptyFilteredPortfolio);
+    }
+    Ok(portfolio)
+}
+
+/// Cancels the program once the time limit has passed, counting from when the solvers of the initial schedule
+/// are compiled.
+fn spawn_time_limit(
+    time_limit: Duration,
+    initial_schedule: &Portfolio,
+    compilation_manager: Arc<CompilationManager>,
+    time_limit_token: CancellationToken,
+    program_cancellation_token: CancellationToken,
+) {
+    let solver_names: Vec<String> = initial_schedule
+        .iter()
+        .map(|info| info.name.clone())
+        .collect();
+
+    tokio::spawn(async move {
+        let wait_for_compilations = async {
+            // Starting is a no-op for the compilations that were already started when applying the schedule
+            compilation_manager
+                .start_many(solver_names.iter().cloned())
+                .await;
+            join_all(
+                solver_names
+                    .iter()
+                    .map(|name| compilation_manager.wait_for(name)),
+            )
+            .await;
+            logging::info!("initial compilations done, the time limit starts now");
+            sleep(time_limit).await;
+        };
+
+        tokio::select! {
+            _ = wait_for_compilations => {
+                logging::info!("time limit reached, stopping");
+                time_limit_token.cancel();
+                program_cancellation_token.cancel();
             }
+            _ = program_cancellation_token.cancelled() => {}
         }
+    });
+}
 
-        timer = sleep(restart_interval);
+/// Resolves once the best objective improves, or never if the solvers are not restarted on improvements
+async fn objective_improved(
+    objective_rx: &mut watch::Receiver<Option<ObjectiveValue>>,
+    rerun_on_improvement: bool,
+) {
+    // The sender lives as long as the solver manager, so a closed channel is never an improvement either
+    if !rerun_on_improvement || objective_rx.changed().await.is_err() {
+        std::future::pending().await
     }
 }
 
+fn cancellation_error(
+    time_limit_token: &CancellationToken,
+    solver_manager: &SolverManager,
+) -> Error {
+    // Satisfaction problems never have an objective, so the printed solutions are counted instead
+    if time_limit_token.is_cancelled() && solver_manager.printed_solution_count() == 0 {
+        Error::TimeLimitWithoutSolution
+    } else {
+        Error::Cancelled
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 async fn start_with_ai<T: Ai + Send + 'static>(
     args: &RunArgs,
     mut ai: T,
+    mut ab_test_ai: Option<T>,
     scheduler: &mut Scheduler,
+    config: &Config,
     initial_schedule: Portfolio,
     cores: usize,
     cancellation_token: CancellationToken,
     compilation_manager: Arc<CompilationManager>,
 ) -> Result<Portfolio, Error> {
+    if let Some(seed) = args.seed {
+        ai.set_seed(seed);
+        if let Some(ab_test_ai) = &mut ab_test_ai {
+            ab_test_ai.set_seed(seed);
+        }
+    }
     let static_runtime_duration = Duration::from_secs(args.static_runtime);
 
     let feature_timeout_duration =
@@ -123,12 +407,18 @@ async fn start_with_ai<T: Ai + Send + 'static>(
         tokio::join!(
             timeout(
                 feature_timeout_duration,
-                get_features(args, compilation_manager, cancellation_token.clone())
+                get_features(
+                    args,
+                    config.expected_feature_length,
+                    compilation_manager,
+                    cancellation_token.clone()
+                )
             ),
             sleep(static_runtime_duration)
         )
     };
     tokio::pin!(barrier);
+    let solver_manager = scheduler.solver_manager.clone();
     let apply_cancellation_token = scheduler.create_apply_token();
     let scheduler_task = scheduler.apply(
         initial_schedule.clone(),
@@ -150,28 +440,39 @@ async fn start_with_ai<T: Ai + Send + 'static>(
             };
             (feat_res, Some(sched_res))
         }
+
+        _ = cancellation_token.cancelled() => {
+            apply_cancellation_token.cancel();
+            return Err(Error::Cancelled);
+        }
     };
 
     let schedule = match features_result {
         Ok(features_result) => {
             let features = features_result?;
-            tokio::task::spawn_blocking(move || ai.schedule(&features, cores)).await??
+            match &mut ab_test_ai {
+                Some(ab_test_ai) => {
+                    ab_test_schedule(
+                        args,
+                        [&mut ai, ab_test_ai],
+                        &features,
+                        cores,
+                        solver_manager.solver_info(),
+                    )
+                    .await?
+                }
+                None => schedule_with_ai(&mut ai, &features, cores, initial_schedule).await?,
+            }
         }
         Err(_) => {
             logging::info!("Feature extraction timed out. Running timeout schedule");
-            timeout_schedule(args, cores).await?
+            timeout_schedule(args, cores, solver_manager.solver_info()).await?
         }
     };
 
     match static_schedule_finished {
         Some(Ok(())) => {}
-        Some(Err(errors)) => {
-            let error_len = errors.len();
-            handle_schedule_errors(errors);
-            if error_len == initial_schedule.len() {
-                return Err(Error::SolverFailure);
-            }
-        }
+        Some(Err(errors)) => handle_apply_errors(errors, &solver_manager).await?,
         None => {
             logging::info!("applying static schedule timed out");
         }
@@ -179,15 +480,68 @@ async fn start_with_ai<T: Ai + Send + 'static>(
     Ok(schedule)
 }
 
+/// Falls back to the given schedule if the AI cannot be reached
+async fn schedule_with_ai(
+    ai: &mut impl Ai,
+    features: &ai::Features,
+    cores: usize,
+    fallback: Portfolio,
+) -> Result<Portfolio, Error> {
+    match ai.schedule(features, cores).await {
+        Ok(schedule) => Ok(schedule),
+        Err(e @ ai::Error::Unavailable(_)) => {
+            logging::warning!("{e}. Continuing with the static schedule");
+            Ok(fallback)
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Splits the cores evenly between the AIs of both sessions, and tags the solvers with the session that
+/// scheduled them. A session whose AI cannot be reached runs the static schedule on its cores.
+async fn ab_test_schedule<T: Ai>(
+    args: &RunArgs,
+    ais: [&mut T; 2],
+    features: &ai::Features,
+    cores: usize,
+    solvers: &solver_config::Solvers,
+) -> Result<Portfolio, Error> {
+    let ab_test_cores = cores / 2;
+    let sessions = ["a", "b"]
+        .into_iter()
+        .zip(ais)
+        .zip([cores - ab_test_cores, ab_test_cores]);
+
+    let mut schedule = Portfolio::new();
+    for ((session, ai), session_cores) in sessions {
+        let fallback = static_schedule(args, session_cores, solvers).await?;
+        let portfolio = schedule_with_ai(ai, features, session_cores, fallback).await?;
+        logging::info!(
+            "A/B test session '{session}' scheduled on {session_cores} cores: {portfolio:?}"
+        );
+        schedule.extend(portfolio.into_iter().map(|mut info| {
+            info.session = Some(session.to_owned());
+            info
+        }));
+    }
+    Ok(schedule)
+}
+
 async fn start_without_ai(
     args: &RunArgs,
     scheduler: &mut Scheduler,
     schedule: Portfolio,
+    cancellation_token: CancellationToken,
 ) -> Result<Portfolio, Error> {
     let static_runtime = Duration::from_secs(args.static_runtime);
 
+    let solver_manager = scheduler.solver_manager.clone();
     let apply_cancellation_token = scheduler.create_apply_token();
-    let fut = scheduler.apply(schedule.clone(), apply_cancellation_token.clone(), true);
+    let fut = scheduler.apply(
+        schedule.clone(),
+        apply_cancellation_token.clone(),
+        !args.precompile_all,
+    );
     tokio::pin!(fut);
 
     let apply_result = tokio::select! {
@@ -199,23 +553,21 @@ async fn start_without_ai(
             logging::info!("applying static schedule timed out");
             fut.await
         }
+        _ = cancellation_token.cancelled() => {
+            apply_cancellation_token.cancel();
+            return Err(Error::Cancelled);
+        }
     };
 
     match apply_result {
         Ok(()) => {}
-        Err(errors) => {
-            let error_len = errors.len();
-            handle_schedule_errors(errors);
-            if error_len == schedule.len() {
-                return Err(Error::SolverFailure);
-            }
-        }
+        Err(errors) => handle_apply_errors(errors, &solver_manager).await?,
     }
     Ok(schedule)
 }
 
 fn get_cores(args: &RunArgs, ai: &Option<impl Ai>) -> (usize, usize) {
-    let mut cores = args.cores;
+    let mut cores = args.cores();
 
     let initial_solver_cores = if args.pin_yuck && ai.is_some() {
         if cores <= 1 {
@@ -231,26 +583,116 @@ fn get_cores(args: &RunArgs, ai: &Option<impl Ai>) -> (usize, usize) {
 
 async fn get_features(
     args: &RunArgs,
+    expected_feature_length: Option<usize>,
     compilation_manager: Arc<CompilationManager>,
     token: CancellationToken,
 ) -> Result<Vec<f32>, Error> {
-    compilation_manager
-        .start(args.feature_extraction_solver_id.clone())
-        .await;
-    let conversion = token
-        .run_until_cancelled(compilation_manager.wait_for(&args.feature_extraction_solver_id))
-        .await
-        .ok_or(Error::Cancelled)??;
+    let conversion = try_compile_for_features(args, &compilation_manager, &token).await?;
 
     tokio::select! {
-        result = fzn_to_features(conversion.fzn()) => {
+        result = fzn_to_features(conversion.fzn(), &args.mzn2feat_exe, expected_feature_length, !args.no_feature_cache) => {
             result.map_err(Error::from)
         },
         _ = token.cancelled() => Err(Error::Cancelled)
     }
 }
 
+/// Compiles the model for feature extraction, starting with the designated feature extraction solver and
+/// falling back to the other known solvers in turn if the compilation fails.
+async fn try_compile_for_features(
+    args: &RunArgs,
+    compilation_manager: &CompilationManager,
+    token: &CancellationToken,
+) -> Result<Arc<Conversion>, Error> {
+    let first_solver_id = args.feature_extraction_solver_id.as_str();
+    let solver_ids = std::iter::once(first_solver_id).chain(
+        solvers::ALL_IDS
+            .into_iter()
+            .filter(|solver_id| *solver_id != first_solver_id),
+    );
+
+    let mut last_error = WaitForError::NotStarted(first_solver_id.to_owned());
+    for solver_id in solver_ids {
+        compilation_manager.start(solver_id.to_owned()).await;
+        let result = token
+            .run_until_cancelled(compilation_manager.wait_for(solver_id))
+            .await
+            .ok_or(Error::Cancelled)?;
+
+        match result {
+            Ok(conversion) => return Ok(conversion),
+            Err(e) if e.is_cancelled() => return Err(Error::Cancelled),
+            Err(e) => {
+                logging::warning!(
+                    "compilation for feature extraction failed for solver '{solver_id}', trying the next solver"
+                );
+                last_error = e;
+            }
+        }
+    }
+
+    Err(last_error.into())
+}
+
+/// Only fails if no solvers are active anymore, since otherwise the remaining solvers can still make progress.
+async fn handle_apply_errors(
+    errors: Vec<solver_manager::Error>,
+    solver_manager: &SolverManager,
+) -> Result<(), Error> {
+    handle_schedule_errors(errors);
+    if solver_manager.active_solver_count().await == 0 {
+        return Err(Error::SolverFailure);
+    }
+    Ok(())
+}
+
 fn handle_schedule_errors(errors: Vec<solver_manager::Error>) {
     logging::error_msg!("got the following errors when applying the schedule:");
-    errors.into_iter().for_each(|e| logging::error!(e.into()));
+    for e in errors {
+        match e {
+            // Not retryable, e.g. the solver already exited, but it does not affect the other solvers
+            solver_manager::Error::InvalidSolver(_) => logging::warning!("{e}"),
+            // The failure was logged when the compilation failed, and the other solvers keep running
+            solver_manager::Error::CompilationFailed { .. } => {
+                logging::warning!("skipping solver: {e}")
+            }
+            // Possibly transient, the solver is started again the next time the schedule is applied
+            _ => logging::error!(e.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROMPTLY: Duration = Duration::from_millis(100);
+
+    #[tokio::test]
+    async fn test_improvement_triggers_a_restart() {
+        let (tx, mut rx) = watch::channel(None);
+        tx.send_replace(Some(ObjectiveValue::Int(10)));
+        assert!(
+            timeout(PROMPTLY, objective_improved(&mut rx, true))
+                .await
+                .is_ok()
+        );
+        // The improvement is consumed, so the next restart waits for the next improvement
+        assert!(
+            timeout(PROMPTLY, objective_improved(&mut rx, true))
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_improvement_is_ignored_without_rerun_on_improvement() {
+        let (tx, mut rx) = watch::channel(None);
+        tx.send_replace(Some(ObjectiveValue::Int(10)));
+        assert!(
+            timeout(PROMPTLY, objective_improved(&mut rx, false))
+                .await
+                .is_err()
+        );
+    }
 }
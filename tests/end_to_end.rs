@@ -1,5 +1,8 @@
 use assert_cmd::Command;
+use predicates::prelude::PredicateBooleanExt;
+use predicates::prelude::predicate;
 use predicates::prelude::predicate::str::contains; // Used for string matching
+use std::time::{Duration, Instant};
 
 fn command() -> Command {
     let path = assert_cmd::cargo::cargo_bin!("parasol");
@@ -18,10 +21,29 @@ fn test_cli_success() {
         "info",
     ])
     .assert()
-    .success()
+    .code(3)
     .stdout(contains("=========="));
 }
 
+#[test]
+fn test_cli_satisfaction() {
+    let mut cmd = command();
+    // 3 if the solver reports that it found every solution
+    cmd.args(["tests/data/sat.mzn"])
+        .assert()
+        .code(predicate::in_iter([0, 3]))
+        .stdout(contains("x = ").and(contains("----------")));
+}
+
+#[test]
+fn test_cli_unsatisfiable() {
+    let mut cmd = command();
+    cmd.args(["tests/data/unsat.mzn"])
+        .assert()
+        .code(1)
+        .stdout(contains("=====UNSATISFIABLE====="));
+}
+
 #[test]
 fn test_cli_failure() {
     let mut cmd = command();
@@ -30,3 +52,238 @@ fn test_cli_failure() {
         .failure()
         .stderr(contains("ERROR"));
 }
+
+#[test]
+fn test_cli_missing_model() {
+    let mut cmd = command();
+    cmd.args(["tests/data/does_not_exist.mzn"])
+        .assert()
+        .code(4)
+        .stderr(contains("Model file not found"));
+}
+
+#[test]
+fn test_cli_missing_model_is_reported_when_quiet() {
+    let mut cmd = command();
+    cmd.args(["tests/data/does_not_exist.mzn", "-v", "quiet"])
+        .assert()
+        .code(4)
+        .stderr(contains("Model file not found"));
+}
+
+#[test]
+fn test_cli_usage_error_exit_code() {
+    let mut cmd = command();
+    cmd.args(["tests/data/accap.mzn", "--no-such-flag"])
+        .assert()
+        .code(4)
+        .stderr(contains("--no-such-flag"));
+}
+
+#[test]
+fn test_cli_missing_extra_data_file() {
+    let mut cmd = command();
+    cmd.args([
+        "tests/data/accap.mzn",
+        "tests/data/accap_instance6.dzn",
+        "-d",
+        "tests/data/does_not_exist.dzn",
+    ])
+    .assert()
+    .failure()
+    .stderr(contains(
+        "Data file not found: tests/data/does_not_exist.dzn",
+    ));
+}
+
+#[test]
+fn test_text_log_format() {
+    let mut cmd = command();
+    cmd.args([
+        "tests/data/accap.mzn",
+        "--ai",
+        "command-line",
+        "--ai-config",
+        "timeout=1",
+    ])
+    .assert()
+    .failure()
+    .stderr(contains("ERROR: [src/main.rs:").and(contains("'command' not provided")));
+}
+
+#[test]
+fn test_json_log_format() {
+    let mut cmd = command();
+    cmd.args([
+        "tests/data/accap.mzn",
+        "--ai",
+        "command-line",
+        "--ai-config",
+        "timeout=1",
+        "--log-format",
+        "json",
+    ])
+    .assert()
+    .failure()
+    .stderr(
+        contains(r#"{"level":"error","file":"src/main.rs","line":"#)
+            .and(contains(r#""msg":"'command' not provided"#))
+            .and(contains(r#","ts":"#)),
+    );
+}
+
+#[test]
+fn test_http_ai_without_url() {
+    let mut cmd = command();
+    cmd.args([
+        "tests/data/accap.mzn",
+        "--ai",
+        "http",
+        "--ai-config",
+        "timeout=1",
+    ])
+    .assert()
+    .failure()
+    .stderr(contains("'url' not provided"));
+}
+
+#[test]
+fn test_output_file_in_missing_directory() {
+    let mut cmd = command();
+    cmd.args([
+        "tests/data/accap.mzn",
+        "--output-file",
+        "tests/data/does_not_exist/solutions.txt",
+    ])
+    .assert()
+    .failure()
+    .stderr(contains("Failed to open the output file"));
+}
+
+#[test]
+fn test_dry_run() {
+    let mut cmd = command();
+    cmd.args(["tests/data/accap.mzn", "--dry-run", "-p", "2"])
+        .assert()
+        .success()
+        .stdout(contains("Dry run, the following solvers would be started"));
+}
+
+#[test]
+fn test_invalid_config_file() {
+    let mut cmd = command();
+    cmd.args([
+        "tests/data/accap.mzn",
+        "--config",
+        "tests/data/invalid_config.toml",
+    ])
+    .assert()
+    .failure()
+    .stderr(contains("memory_threshold must be in (0, 1]"));
+}
+
+#[test]
+fn test_log_level_env_var() {
+    let mut cmd = command();
+    cmd.env("PARASOL_LOG_LEVEL", "QUIET")
+        .args(["tests/data/accap_instance6.dzn"])
+        .assert()
+        .failure()
+        .stderr(contains("ERROR").not());
+}
+
+#[test]
+fn test_log_level_flag_overrides_env_var() {
+    let mut cmd = command();
+    cmd.env("PARASOL_LOG_LEVEL", "quiet")
+        .args(["tests/data/accap_instance6.dzn", "-v", "error"])
+        .assert()
+        .failure()
+        .stderr(contains("ERROR"));
+}
+
+#[test]
+fn test_cli_interrupt_exits_promptly() {
+    let path = assert_cmd::cargo::cargo_bin!("parasol");
+    let mut child = std::process::Command::new(path)
+        .args([
+            "run",
+            "tests/data/accap.mzn",
+            "tests/data/accap_instance6.dzn",
+            "--static-runtime",
+            "60",
+            "--restart-interval",
+            "60",
+        ])
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .expect("failed to spawn parasol");
+
+    std::thread::sleep(Duration::from_secs(2));
+    nix::sys::signal::kill(
+        nix::unistd::Pid::from_raw(child.id() as i32),
+        nix::sys::signal::Signal::SIGINT,
+    )
+    .expect("failed to send SIGINT");
+
+    let start = Instant::now();
+    while child
+        .try_wait()
+        .expect("failed to wait on parasol")
+        .is_none()
+    {
+        assert!(
+            start.elapsed() < Duration::from_secs(10),
+            "parasol did not exit after being interrupted"
+        );
+        std::thread::sleep(Duration::from_millis(100));
+    }
+}
+
+#[test]
+fn test_ab_test_ai_needs_an_ai() {
+    let mut cmd = command();
+    cmd.args([
+        "tests/data/accap.mzn",
+        "--ai",
+        "none",
+        "--ab-test-ai",
+        "command=./other_ai",
+    ])
+    .assert()
+    .failure()
+    .stderr(contains("--ab-test-ai needs an AI to compare with"));
+}
+
+#[test]
+fn test_ab_test_ai_rejects_the_simple_ai() {
+    let mut cmd = command();
+    cmd.args([
+        "tests/data/accap.mzn",
+        "--ai",
+        "simple",
+        "--ab-test-ai",
+        "command=./other_ai",
+    ])
+    .assert()
+    .code(4)
+    .stderr(contains("the simple AI has no configuration"));
+}
+
+#[test]
+fn test_ab_test_ai_needs_a_different_config() {
+    let mut cmd = command();
+    cmd.args([
+        "tests/data/accap.mzn",
+        "--ai",
+        "command-line",
+        "--ai-config",
+        "command=./ai",
+        "--ab-test-ai",
+        "command=./ai",
+    ])
+    .assert()
+    .code(4)
+    .stderr(contains("both sessions would run the same AI"));
+}